@@ -0,0 +1,71 @@
+//! Times the tree-walker on `fib(30)`, which is dominated by calls, and on a
+//! loop that does nothing but read and write local variables. Run with
+//! `cargo bench`; each workload reports its best of three runs.
+//!
+//! `cargo bench` on the same machine, in seconds. Runs varied by up to a
+//! third from one to the next, so only the size of each step means much:
+//!
+//! | workload | hash map scopes | resolver slots | slots, shared bodies |
+//! |----------|-----------------|----------------|----------------------|
+//! | locals   | 5.61            | 3.50           | 0.96                 |
+//! | fib(30)  | 24.65           | 21.72          | 1.68                 |
+//!
+//! The first two columns are just before and just after locals moved from a
+//! hash map per scope into the slots the resolver assigns. The last column
+//! also shares function bodies and parameters instead of cloning them on
+//! every call.
+
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use std::time::{Duration, Instant};
+
+const FIB: &str = "
+fun fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+fib(30);
+";
+
+const LOCALS: &str = "
+fun run() {
+  var sum = 0;
+  var a = 1;
+  var b = 2;
+  for (var i = 0; i < 1000000; i = i + 1) {
+    var t = a + b;
+    a = b;
+    b = t - a;
+    sum = sum + t;
+  }
+  return sum;
+}
+run();
+";
+
+fn time(source: &str) -> Duration {
+    let tokens = Scanner::new(source.chars().peekable())
+        .scan_tokens()
+        .unwrap();
+    let (stmts, errors) = Parser::new(tokens).parse();
+    assert!(errors.is_empty());
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(Vec::new(), &mut interpreter);
+    resolver.resolve_stmts(&stmts).unwrap();
+
+    let start = Instant::now();
+    interpreter.interpret(stmts).unwrap();
+    start.elapsed()
+}
+
+fn best_of_three(source: &str) -> Duration {
+    (0..3).map(|_| time(source)).min().unwrap()
+}
+
+fn main() {
+    println!("locals:  {:.2?}", best_of_three(LOCALS));
+    println!("fib(30): {:.2?}", best_of_three(FIB));
+}
@@ -0,0 +1,369 @@
+//! Lists and maps, shared by the tree-walker and the bytecode VM. Each
+//! backend has its own value type, so the code here is generic over
+//! `Element`.
+
+use crate::interpreter::RuntimeError;
+use crate::scanner::Token;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::rc::Rc;
+
+/// A value that can be stored in a list or a map.
+pub trait Element: Clone + Eq + Hash + fmt::Display {
+    fn nil() -> Self;
+    fn boolean(b: bool) -> Self;
+    fn number(n: f64) -> Self;
+    fn list(elements: Vec<Self>) -> Self;
+
+    /// The number held by the value, if it is one.
+    fn as_number(&self) -> Option<f64>;
+
+    /// Whether the value is a number, string, boolean or nil, the only
+    /// values with a stable hash.
+    fn is_primitive(&self) -> bool;
+
+    /// The list or map the value holds, if it is one.
+    fn collection(&self) -> Option<Collection<'_, Self>>;
+}
+
+/// A list or map held by an `Element`, for the code that walks nested
+/// collections.
+pub enum Collection<'a, T> {
+    List(&'a Rc<RefCell<Vec<T>>>),
+    Map(&'a Rc<RefCell<LoxMap<T>>>),
+}
+
+impl<T> Collection<'_, T> {
+    fn address(&self) -> *const () {
+        match self {
+            Self::List(l) => Rc::as_ptr(l) as *const (),
+            Self::Map(m) => Rc::as_ptr(m) as *const (),
+        }
+    }
+}
+
+/// Formats a list or map, printing `[...]` or `{...}` for one inside itself.
+pub fn fmt_collection<T: Element>(
+    collection: Collection<'_, T>,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    write_collection(collection, f, &mut Vec::new())
+}
+
+/// `path` holds the collections being printed around this one.
+fn write_collection<T: Element>(
+    collection: Collection<'_, T>,
+    f: &mut fmt::Formatter<'_>,
+    path: &mut Vec<*const ()>,
+) -> fmt::Result {
+    let address = collection.address();
+    if path.contains(&address) {
+        return match collection {
+            Collection::List(_) => write!(f, "[...]"),
+            Collection::Map(_) => write!(f, "{{...}}"),
+        };
+    }
+
+    path.push(address);
+    match collection {
+        Collection::List(l) => {
+            write!(f, "[")?;
+            for (i, element) in l.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_element(element, f, path)?;
+            }
+            write!(f, "]")?;
+        }
+        Collection::Map(m) => {
+            write!(f, "{{")?;
+            for (i, (key, value)) in m.borrow().entries().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: ", key)?;
+                write_element(value, f, path)?;
+            }
+            write!(f, "}}")?;
+        }
+    }
+    path.pop();
+
+    Ok(())
+}
+
+fn write_element<T: Element>(
+    element: &T,
+    f: &mut fmt::Formatter<'_>,
+    path: &mut Vec<*const ()>,
+) -> fmt::Result {
+    match element.collection() {
+        Some(collection) => write_collection(collection, f, path),
+        None => write!(f, "{}", element),
+    }
+}
+
+/// Compares two lists or maps element by element, matching map entries by
+/// key rather than by position. A collection is always equal to itself, and
+/// a pair that is already being compared further out is taken to be equal,
+/// so collections that contain themselves don't recurse forever.
+pub fn eq_collections<T: Element>(l: Collection<'_, T>, r: Collection<'_, T>) -> bool {
+    eq_nested(l, r, &mut Vec::new())
+}
+
+fn eq_nested<T: Element>(
+    l: Collection<'_, T>,
+    r: Collection<'_, T>,
+    path: &mut Vec<(*const (), *const ())>,
+) -> bool {
+    let pair = (l.address(), r.address());
+    if pair.0 == pair.1 || path.contains(&pair) {
+        return true;
+    }
+
+    path.push(pair);
+    let equal = match (l, r) {
+        (Collection::List(l), Collection::List(r)) => {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.len() == r.len() && l.iter().zip(r.iter()).all(|(a, b)| eq_elements(a, b, path))
+        }
+        (Collection::Map(l), Collection::Map(r)) => {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.len() == r.len()
+                && l.entries().iter().all(|(k, lv)| match r.get(k) {
+                    Some(rv) => eq_elements(lv, &rv, path),
+                    None => false,
+                })
+        }
+        _ => false,
+    };
+    path.pop();
+
+    equal
+}
+
+fn eq_elements<T: Element>(l: &T, r: &T, path: &mut Vec<(*const (), *const ())>) -> bool {
+    match (l.collection(), r.collection()) {
+        (Some(l), Some(r)) => eq_nested(l, r, path),
+        _ => l == r,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListMethod {
+    PUSH,
+    POP,
+    LEN,
+    INSERT,
+    REMOVE,
+}
+
+impl ListMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "push" => Some(Self::PUSH),
+            "pop" => Some(Self::POP),
+            "len" => Some(Self::LEN),
+            "insert" => Some(Self::INSERT),
+            "remove" => Some(Self::REMOVE),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn arity(&self) -> usize {
+        match self {
+            Self::POP | Self::LEN => 0,
+            Self::PUSH | Self::REMOVE => 1,
+            Self::INSERT => 2,
+        }
+    }
+
+    /// Runs the method on `list`. The arity has already been checked.
+    pub fn call<T: Element>(
+        &self,
+        list: &mut Vec<T>,
+        params: &[T],
+        token: &Token,
+    ) -> Result<T, RuntimeError> {
+        match self {
+            Self::PUSH => {
+                list.push(params[0].clone());
+                Ok(T::nil())
+            }
+            Self::POP => match list.pop() {
+                Some(v) => Ok(v),
+                _ => Err(RuntimeError::new(
+                    "Can't pop from an empty list.".to_string(),
+                    token.clone(),
+                )),
+            },
+            Self::LEN => Ok(T::number(list.len() as f64)),
+            Self::INSERT => {
+                let i = list_index(&params[0], list.len() + 1, token)?;
+                list.insert(i, params[1].clone());
+                Ok(T::nil())
+            }
+            Self::REMOVE => {
+                let i = list_index(&params[0], list.len(), token)?;
+                Ok(list.remove(i))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapMethod {
+    KEYS,
+    VALUES,
+    HAS,
+    REMOVE,
+    LEN,
+}
+
+impl MapMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keys" => Some(Self::KEYS),
+            "values" => Some(Self::VALUES),
+            "has" => Some(Self::HAS),
+            "remove" => Some(Self::REMOVE),
+            "len" => Some(Self::LEN),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn arity(&self) -> usize {
+        match self {
+            Self::KEYS | Self::VALUES | Self::LEN => 0,
+            Self::HAS | Self::REMOVE => 1,
+        }
+    }
+
+    /// Runs the method on `map`. The arity has already been checked.
+    pub fn call<T: Element>(
+        &self,
+        map: &mut LoxMap<T>,
+        params: &[T],
+        token: &Token,
+    ) -> Result<T, RuntimeError> {
+        match self {
+            Self::KEYS => Ok(T::list(map.keys())),
+            Self::VALUES => Ok(T::list(map.values())),
+            Self::HAS => Ok(T::boolean(map.get(&map_key(&params[0], token)?).is_some())),
+            Self::REMOVE => match map.remove(&map_key(&params[0], token)?) {
+                Some(v) => Ok(v),
+                _ => Err(RuntimeError::new(
+                    format!("Undefined key {}.", params[0]),
+                    token.clone(),
+                )),
+            },
+            Self::LEN => Ok(T::number(map.len() as f64)),
+        }
+    }
+}
+
+/// Checks that `key` can be stored in a map, i.e. that it has a stable hash.
+pub fn map_key<T: Element>(key: &T, token: &Token) -> Result<T, RuntimeError> {
+    match key.as_number() {
+        Some(n) if n.is_nan() => Err(RuntimeError::new(
+            "NaN can't be used as a map key.".to_string(),
+            token.clone(),
+        )),
+        _ if key.is_primitive() => Ok(key.clone()),
+        _ => Err(RuntimeError::new(
+            "Map keys must be numbers, strings, booleans or nil.".to_string(),
+            token.clone(),
+        )),
+    }
+}
+
+/// Converts `index` into a position in a list, checking it against `len`.
+pub fn list_index<T: Element>(index: &T, len: usize, token: &Token) -> Result<usize, RuntimeError> {
+    match index.as_number() {
+        Some(n) if n.fract() == 0.0 => {
+            if n < 0.0 || n >= len as f64 {
+                return Err(RuntimeError::new(
+                    format!("Index {} out of bounds for length {}.", n, len),
+                    token.clone(),
+                ));
+            }
+            Ok(n as usize)
+        }
+        _ => Err(RuntimeError::new(
+            "List index must be an integer.".to_string(),
+            token.clone(),
+        )),
+    }
+}
+
+/// An insertion-ordered hash map. Keys are validated with `map_key` before
+/// they get here, so every key hashes consistently with its `PartialEq`.
+#[derive(Debug, Clone)]
+pub struct LoxMap<T> {
+    entries: Vec<(T, T)>,
+    index: HashMap<T, usize>,
+}
+
+impl<T> Default for LoxMap<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> LoxMap<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.index.get(key).map(|i| self.entries[*i].1.clone())
+    }
+
+    pub fn insert(&mut self, key: T, value: T) {
+        match self.index.get(&key) {
+            Some(i) => self.entries[*i].1 = value,
+            _ => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &T) -> Option<T> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (_, position) in self.index.iter_mut() {
+            if *position > i {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn keys(&self) -> Vec<T> {
+        self.entries.iter().map(|(k, _)| k.clone()).collect()
+    }
+
+    pub fn values(&self) -> Vec<T> {
+        self.entries.iter().map(|(_, v)| v.clone()).collect()
+    }
+
+    /// The key-value pairs, in insertion order.
+    pub fn entries(&self) -> &[(T, T)] {
+        &self.entries
+    }
+}
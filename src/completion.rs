@@ -0,0 +1,25 @@
+use crate::scanner::KEYWORDS;
+
+/// Completes the identifier that ends at byte `pos` of `line`. Returns where
+/// the identifier starts and the keywords and `globals` it is a prefix of,
+/// sorted and without duplicates.
+pub fn complete(line: &str, pos: usize, globals: &[String]) -> (usize, Vec<String>) {
+    let start = line[..pos]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !(c.is_alphanumeric() || c == '_'))
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0);
+    let prefix = &line[start..pos];
+
+    let mut candidates: Vec<String> = KEYWORDS
+        .keys()
+        .map(|k| k.to_string())
+        .chain(globals.iter().cloned())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    (start, candidates)
+}
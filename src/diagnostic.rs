@@ -0,0 +1,153 @@
+use crate::error::LoxError;
+use crate::scanner::Span;
+use std::fmt::Write;
+
+const TAB_WIDTH: usize = 4;
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn error(&self, text: &str) -> String {
+        self.paint("1;31", text)
+    }
+
+    fn note(&self, text: &str) -> String {
+        self.paint("1;34", text)
+    }
+
+    fn bold(&self, text: &str) -> String {
+        self.paint("1", text)
+    }
+}
+
+/// An underline to draw beneath one source line.
+struct Mark {
+    line: usize,
+    column: usize,
+    width: usize,
+    primary: bool,
+    message: String,
+}
+
+impl Mark {
+    fn new(source: &str, span: Span, primary: bool, message: &str) -> Self {
+        let start = span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[start..]
+            .find('\n')
+            .map(|i| start + i)
+            .unwrap_or(source.len());
+        let end = span.end.clamp(start, line_end);
+
+        Self {
+            line: source[..start].matches('\n').count(),
+            column: display_width(&source[line_start..start]),
+            width: display_width(&source[start..end]).max(1),
+            primary,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+pub fn render(error: &LoxError, source: &str, file_name: &str, color: bool) -> String {
+    let style = Style { color };
+    let location = error.location();
+    let lines: Vec<&str> = source.split('\n').collect();
+
+    let mut marks = vec![Mark::new(source, location.span, true, "")];
+    for label in error.labels() {
+        marks.push(Mark::new(source, label.span, false, &label.message));
+    }
+    marks.sort_by_key(|m| (m.line, !m.primary));
+
+    let last_line = marks.iter().map(|m| m.line).max().unwrap_or(0) + 1;
+    let gutter = " ".repeat(last_line.to_string().len());
+
+    let mut out = String::new();
+    let header = format!("{} error", error.kind().to_lowercase());
+    let _ = writeln!(
+        out,
+        "{}: {}",
+        style.error(&header),
+        style.bold(error.message())
+    );
+    let _ = writeln!(
+        out,
+        "{}{} {}:{}:{}",
+        gutter,
+        style.note("-->"),
+        file_name,
+        location.line,
+        location.column
+    );
+    let _ = writeln!(out, "{} {}", gutter, style.note("|"));
+
+    let mut previous_line = None;
+    for mark in &marks {
+        if previous_line != Some(mark.line) {
+            if let Some(p) = previous_line
+                && mark.line > p + 1
+            {
+                let _ = writeln!(out, "{}", style.note("..."));
+            }
+
+            let text = lines.get(mark.line).copied().unwrap_or("");
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                style.note(&format!("{:>width$}", mark.line + 1, width = gutter.len())),
+                style.note("|"),
+                text.replace('\t', &" ".repeat(TAB_WIDTH))
+            );
+            previous_line = Some(mark.line);
+        }
+
+        let underline = if mark.primary {
+            style.error(&"^".repeat(mark.width))
+        } else {
+            style.note(&"-".repeat(mark.width))
+        };
+        let _ = write!(
+            out,
+            "{} {} {}{}",
+            gutter,
+            style.note("|"),
+            " ".repeat(mark.column),
+            underline
+        );
+        if !mark.message.is_empty() {
+            let message = if mark.primary {
+                style.error(&mark.message)
+            } else {
+                style.note(&mark.message)
+            };
+            let _ = write!(out, " {}", message);
+        }
+        out.push('\n');
+    }
+
+    if !error.trace().is_empty() {
+        let _ = writeln!(out, "{} {} stack trace:", gutter, style.note("="));
+        for frame in error.trace() {
+            let _ = writeln!(out, "{}   {}", gutter, frame);
+        }
+    }
+
+    out.trim_end().to_string()
+}
@@ -0,0 +1,185 @@
+use crate::diagnostic;
+use crate::interpreter::RuntimeError;
+use crate::scanner::{Span, Token, TokenType};
+use std::fmt;
+
+/// Where in the source an error was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub line: u64,
+    pub column: u64,
+    pub span: Span,
+    /// The text of the offending token, or `end` for the end of the input.
+    pub lexeme: Option<String>,
+}
+
+impl From<&Token> for Location {
+    fn from(token: &Token) -> Self {
+        let lexeme = match token.token_type {
+            TokenType::EOF => "end".to_string(),
+            _ => token.lexeme.clone(),
+        };
+
+        Self {
+            line: token.line,
+            column: token.column,
+            span: token.span,
+            lexeme: Some(lexeme),
+        }
+    }
+}
+
+/// A secondary note pointing at another part of the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// One line of a runtime error's backtrace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub function: String,
+    pub line: u64,
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] in {}", self.line, self.function)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Location,
+    pub labels: Vec<Label>,
+    /// The calls active when a runtime error happened, innermost first.
+    pub trace: Vec<StackFrame>,
+}
+
+impl Diagnostic {
+    pub fn new(location: Location, message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            location,
+            labels: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoxError {
+    Scan(Box<Diagnostic>),
+    Parse(Box<Diagnostic>),
+    Resolve(Box<Diagnostic>),
+    Compile(Box<Diagnostic>),
+    Runtime(Box<Diagnostic>),
+}
+
+impl LoxError {
+    pub fn scan(location: Location, message: &str) -> Self {
+        Self::Scan(Box::new(Diagnostic::new(location, message)))
+    }
+
+    pub fn parse(token: &Token, message: &str) -> Self {
+        Self::Parse(Box::new(Diagnostic::new(Location::from(token), message)))
+    }
+
+    pub fn resolve(token: &Token, message: &str) -> Self {
+        Self::Resolve(Box::new(Diagnostic::new(Location::from(token), message)))
+    }
+
+    pub fn compile(token: &Token, message: &str) -> Self {
+        Self::Compile(Box::new(Diagnostic::new(Location::from(token), message)))
+    }
+
+    /// Adds a secondary label, e.g. pointing at an earlier declaration.
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.diagnostic_mut().labels.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Scan(_) => "Scan",
+            Self::Parse(_) => "Parse",
+            Self::Resolve(_) => "Resolve",
+            Self::Compile(_) => "Compile",
+            Self::Runtime(_) => "Runtime",
+        }
+    }
+
+    pub fn diagnostic(&self) -> &Diagnostic {
+        match self {
+            Self::Scan(d)
+            | Self::Parse(d)
+            | Self::Resolve(d)
+            | Self::Compile(d)
+            | Self::Runtime(d) => d,
+        }
+    }
+
+    fn diagnostic_mut(&mut self) -> &mut Diagnostic {
+        match self {
+            Self::Scan(d)
+            | Self::Parse(d)
+            | Self::Resolve(d)
+            | Self::Compile(d)
+            | Self::Runtime(d) => d,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.diagnostic().message
+    }
+
+    pub fn location(&self) -> &Location {
+        &self.diagnostic().location
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.diagnostic().labels
+    }
+
+    pub fn trace(&self) -> &[StackFrame] {
+        &self.diagnostic().trace
+    }
+
+    /// Renders the error with the offending source lines underlined, in the
+    /// style of rustc. `color` adds ANSI colours for terminals.
+    pub fn render(&self, source: &str, file_name: &str, color: bool) -> String {
+        diagnostic::render(self, source, file_name, color)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(error: RuntimeError) -> Self {
+        let mut diagnostic = Diagnostic::new(Location::from(error.token()), error.message());
+        diagnostic.trace = error.trace().to_vec();
+        Self::Runtime(Box::new(diagnostic))
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = self.location();
+        write!(
+            f,
+            "[line {}:{}] {} error",
+            location.line,
+            location.column,
+            self.kind()
+        )?;
+        if let Some(lexeme) = &location.lexeme {
+            write!(f, " at '{}'", lexeme)?;
+        }
+        write!(f, ": {}", self.message())
+    }
+}
+
+impl std::error::Error for LoxError {}
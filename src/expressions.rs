@@ -1,5 +1,7 @@
-use crate::scanner::Token;
+use crate::scanner::{Span, Token};
+use crate::statements::Stmt;
 use crate::types::Object;
+use std::rc::Rc;
 
 type ExprID = usize;
 
@@ -15,10 +17,17 @@ pub trait VisitorE<T> {
     fn visit_get(&mut self, expr: &Get) -> T;
     fn visit_set(&mut self, expr: &Set) -> T;
     fn visit_this(&mut self, expr: &This) -> T;
+    fn visit_super(&mut self, expr: &Super) -> T;
+    fn visit_list(&mut self, expr: &List) -> T;
+    fn visit_map(&mut self, expr: &Map) -> T;
+    fn visit_index(&mut self, expr: &Index) -> T;
+    fn visit_set_index(&mut self, expr: &SetIndex) -> T;
+    fn visit_interpolation(&mut self, expr: &Interpolation) -> T;
+    fn visit_lambda(&mut self, expr: &Lambda) -> T;
 }
 
 pub trait VisitableE<T> {
-    fn accept(&mut self, visitor: &mut impl VisitorE<T>) -> T;
+    fn accept(&self, visitor: &mut impl VisitorE<T>) -> T;
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -34,6 +43,13 @@ pub enum Expr {
     Get(Get),
     Set(Set),
     This(This),
+    Super(Super),
+    List(List),
+    Map(Map),
+    Index(Index),
+    SetIndex(SetIndex),
+    Interpolation(Interpolation),
+    Lambda(Lambda),
 }
 
 impl Expr {
@@ -50,12 +66,42 @@ impl Expr {
             Self::Get(g) => g.id,
             Self::Set(s) => s.id,
             Self::This(t) => t.id,
+            Self::Super(s) => s.id,
+            Self::List(l) => l.id,
+            Self::Map(m) => m.id,
+            Self::Index(i) => i.id,
+            Self::SetIndex(s) => s.id,
+            Self::Interpolation(i) => i.id,
+            Self::Lambda(l) => l.id,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Literal(l) => l.span,
+            Self::Binary(b) => b.span,
+            Self::Unary(u) => u.span,
+            Self::Grouping(g) => g.span,
+            Self::Var(v) => v.span,
+            Self::Assign(a) => a.span,
+            Self::Logical(l) => l.span,
+            Self::Call(c) => c.span,
+            Self::Get(g) => g.span,
+            Self::Set(s) => s.span,
+            Self::This(t) => t.span,
+            Self::Super(s) => s.span,
+            Self::List(l) => l.span,
+            Self::Map(m) => m.span,
+            Self::Index(i) => i.span,
+            Self::SetIndex(s) => s.span,
+            Self::Interpolation(i) => i.span,
+            Self::Lambda(l) => l.span,
         }
     }
 }
 
 impl<T> VisitableE<T> for Expr {
-    fn accept(&mut self, visitor: &mut impl VisitorE<T>) -> T {
+    fn accept(&self, visitor: &mut impl VisitorE<T>) -> T {
         match self {
             Self::Literal(l) => visitor.visit_literal(l),
             Self::Binary(b) => visitor.visit_binary(b),
@@ -68,6 +114,13 @@ impl<T> VisitableE<T> for Expr {
             Self::Get(g) => visitor.visit_get(g),
             Self::Set(s) => visitor.visit_set(s),
             Self::This(t) => visitor.visit_this(t),
+            Self::Super(s) => visitor.visit_super(s),
+            Self::List(l) => visitor.visit_list(l),
+            Self::Map(m) => visitor.visit_map(m),
+            Self::Index(i) => visitor.visit_index(i),
+            Self::SetIndex(s) => visitor.visit_set_index(s),
+            Self::Interpolation(i) => visitor.visit_interpolation(i),
+            Self::Lambda(l) => visitor.visit_lambda(l),
         }
     }
 }
@@ -75,12 +128,14 @@ impl<T> VisitableE<T> for Expr {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Literal {
     pub id: ExprID,
+    pub span: Span,
     pub value: Object,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Binary {
     pub id: ExprID,
+    pub span: Span,
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
@@ -89,6 +144,7 @@ pub struct Binary {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Logical {
     pub id: ExprID,
+    pub span: Span,
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
@@ -97,6 +153,7 @@ pub struct Logical {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Unary {
     pub id: ExprID,
+    pub span: Span,
     pub operator: Token,
     pub right: Box<Expr>,
 }
@@ -104,18 +161,21 @@ pub struct Unary {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Grouping {
     pub id: ExprID,
+    pub span: Span,
     pub expr: Box<Expr>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Variable {
     pub id: ExprID,
+    pub span: Span,
     pub name: Token,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Assign {
     pub id: ExprID,
+    pub span: Span,
     pub name: Token,
     pub value: Box<Expr>,
 }
@@ -123,6 +183,7 @@ pub struct Assign {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Call {
     pub id: ExprID,
+    pub span: Span,
     pub calle: Box<Expr>,
     pub paren: Token,
     pub arguments: Vec<Expr>,
@@ -131,6 +192,7 @@ pub struct Call {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Get {
     pub id: ExprID,
+    pub span: Span,
     pub name: Token,
     pub expr: Box<Expr>,
 }
@@ -138,6 +200,7 @@ pub struct Get {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Set {
     pub id: ExprID,
+    pub span: Span,
     pub name: Token,
     pub expr: Box<Expr>,
     pub value: Box<Expr>,
@@ -146,5 +209,68 @@ pub struct Set {
 #[derive(Clone, Debug, PartialEq)]
 pub struct This {
     pub id: ExprID,
+    pub span: Span,
+    pub keyword: Token,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Super {
+    pub id: ExprID,
+    pub span: Span,
     pub keyword: Token,
+    pub method: Token,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct List {
+    pub id: ExprID,
+    pub span: Span,
+    pub bracket: Token,
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    pub id: ExprID,
+    pub span: Span,
+    pub brace: Token,
+    pub entries: Vec<(Expr, Expr)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index {
+    pub id: ExprID,
+    pub span: Span,
+    pub expr: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetIndex {
+    pub id: ExprID,
+    pub span: Span,
+    pub expr: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+/// A string with embedded `${...}` expressions. The parts are the literal
+/// pieces of the string and the embedded expressions, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interpolation {
+    pub id: ExprID,
+    pub span: Span,
+    pub parts: Vec<Expr>,
+}
+
+/// An anonymous function, `fun (a, b) { ... }` or `(a, b) => expr`. The body
+/// of the arrow form is a single `return` of its expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lambda {
+    pub id: ExprID,
+    pub span: Span,
+    pub params: Rc<[Token]>,
+    pub body: Rc<[Stmt]>,
 }
@@ -0,0 +1,305 @@
+//! The tree-walker's managed heap. Environments, instances and classes live
+//! here rather than behind `Rc`s, because they point at each other in cycles:
+//! a function is stored in the environment it closes over, and an instance can
+//! hold methods bound to itself. A mark-sweep collector frees whatever the
+//! interpreter can no longer reach, cycles included.
+//!
+//! Lists and maps stay behind `Rc`s so that their code is shared with the VM.
+//! The collector traces through them, and they are dropped with whatever
+//! holds them, but a list or map that contains itself only through other
+//! lists and maps is never freed.
+
+use crate::interpreter::Environment;
+use crate::types::{Function, LoxClass, LoxInstance, NativeFunc, Object};
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+
+// Collect once this many objects are live, at the earliest.
+const MIN_THRESHOLD: usize = 1024;
+
+/// A reference to an object on the `Heap`. Only valid while the object is
+/// reachable from the interpreter's roots.
+pub struct Gc<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Gc<T> {
+    fn new(index: usize) -> Self {
+        Self {
+            index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Gc<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Gc<T> {}
+
+impl<T> PartialEq for Gc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Gc<T> {}
+
+impl<T> fmt::Debug for Gc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gc({})", self.index)
+    }
+}
+
+pub enum HeapObject {
+    Env(Environment),
+    Instance(LoxInstance),
+    Class(LoxClass),
+}
+
+/// A type that can be stored on the heap.
+pub trait Managed: Trace + Sized {
+    fn into_object(self) -> HeapObject;
+    fn from_object(object: &HeapObject) -> &Self;
+    fn from_object_mut(object: &mut HeapObject) -> &mut Self;
+}
+
+macro_rules! managed {
+    ($type:ty, $variant:ident) => {
+        impl Managed for $type {
+            fn into_object(self) -> HeapObject {
+                HeapObject::$variant(self)
+            }
+
+            fn from_object(object: &HeapObject) -> &Self {
+                match object {
+                    HeapObject::$variant(o) => o,
+                    _ => unreachable!(),
+                }
+            }
+
+            fn from_object_mut(object: &mut HeapObject) -> &mut Self {
+                match object {
+                    HeapObject::$variant(o) => o,
+                    _ => unreachable!(),
+                }
+            }
+        }
+    };
+}
+
+managed!(Environment, Env);
+managed!(LoxInstance, Instance);
+managed!(LoxClass, Class);
+
+/// Counters reported by the `gcStats()` native.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcStats {
+    pub collections: usize,
+    pub allocated: usize,
+    pub freed: usize,
+    pub live: usize,
+}
+
+pub struct Heap {
+    objects: Vec<Option<HeapObject>>,
+    marks: Vec<bool>,
+    // Slots of freed objects, reused before the heap grows.
+    free: Vec<usize>,
+    // The number of live objects at which to collect next.
+    threshold: usize,
+    // Collect on every allocation, to flush out missing roots.
+    stress: bool,
+    stats: GcStats,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            marks: Vec::new(),
+            free: Vec::new(),
+            threshold: MIN_THRESHOLD,
+            stress: false,
+            stats: GcStats::default(),
+        }
+    }
+
+    pub fn set_stress(&mut self, stress: bool) {
+        self.stress = stress;
+    }
+
+    pub fn stats(&self) -> GcStats {
+        self.stats
+    }
+
+    pub fn insert<T: Managed>(&mut self, object: T) -> Gc<T> {
+        self.stats.allocated += 1;
+        self.stats.live += 1;
+
+        let object = Some(object.into_object());
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index] = object;
+                Gc::new(index)
+            }
+            None => {
+                self.objects.push(object);
+                self.marks.push(false);
+                Gc::new(self.objects.len() - 1)
+            }
+        }
+    }
+
+    pub fn get<T: Managed>(&self, gc: Gc<T>) -> &T {
+        T::from_object(self.objects[gc.index].as_ref().unwrap())
+    }
+
+    pub fn get_mut<T: Managed>(&mut self, gc: Gc<T>) -> &mut T {
+        T::from_object_mut(self.objects[gc.index].as_mut().unwrap())
+    }
+
+    /// Whether enough has been allocated since the last collection to run
+    /// another.
+    pub fn should_collect(&self) -> bool {
+        self.stress || self.stats.live >= self.threshold
+    }
+
+    /// Frees every object that isn't reachable from what `roots` marks.
+    pub fn collect(&mut self, roots: impl FnOnce(&mut Tracer)) {
+        let mut tracer = Tracer {
+            marks: &mut self.marks,
+            gray: Vec::new(),
+            seen: HashSet::new(),
+        };
+        roots(&mut tracer);
+
+        while let Some(index) = tracer.gray.pop() {
+            match self.objects[index].as_ref().unwrap() {
+                HeapObject::Env(env) => env.trace(&mut tracer),
+                HeapObject::Instance(instance) => instance.trace(&mut tracer),
+                HeapObject::Class(class) => class.trace(&mut tracer),
+            }
+        }
+
+        for index in 0..self.objects.len() {
+            if self.marks[index] {
+                self.marks[index] = false;
+            } else if self.objects[index].take().is_some() {
+                self.free.push(index);
+                self.stats.freed += 1;
+                self.stats.live -= 1;
+            }
+        }
+
+        self.stats.collections += 1;
+        self.threshold = (self.stats.live * 2).max(MIN_THRESHOLD);
+    }
+}
+
+/// Marks the objects reachable from the roots during a collection.
+pub struct Tracer<'a> {
+    marks: &'a mut Vec<bool>,
+    // Marked objects whose references are still to be traced.
+    gray: Vec<usize>,
+    // Lists and maps already traced, since they can contain themselves.
+    seen: HashSet<*const ()>,
+}
+
+impl Tracer<'_> {
+    pub fn mark<T>(&mut self, gc: Gc<T>) {
+        if !self.marks[gc.index] {
+            self.marks[gc.index] = true;
+            self.gray.push(gc.index);
+        }
+    }
+
+    fn first_visit<T>(&mut self, pointer: *const T) -> bool {
+        self.seen.insert(pointer as *const ())
+    }
+}
+
+/// Something that can hold references to objects on the heap.
+pub trait Trace {
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+impl Trace for Object {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Self::Func(f) => f.trace(tracer),
+            Self::Class(c) => tracer.mark(c.gc),
+            Self::ClassInstance(i) => tracer.mark(i.gc),
+            Self::List(l) | Self::NativeFunc(NativeFunc::LIST(l, _))
+                if tracer.first_visit(l.as_ptr()) =>
+            {
+                for element in l.borrow().iter() {
+                    element.trace(tracer);
+                }
+            }
+            Self::Map(m) | Self::NativeFunc(NativeFunc::MAP(m, _))
+                if tracer.first_visit(m.as_ptr()) =>
+            {
+                for (key, value) in m.borrow().entries() {
+                    key.trace(tracer);
+                    value.trace(tracer);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Trace for Function {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(closure) = self.closure {
+            tracer.mark(closure);
+        }
+        if let Some((instance, method)) = &self.bound {
+            tracer.mark(instance.gc);
+            method.trace(tracer);
+        }
+    }
+}
+
+impl Trace for Environment {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(enclosing) = self.enclosing {
+            tracer.mark(enclosing);
+        }
+        for value in &self.slots {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl Trace for LoxInstance {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.mark(self.klass.gc);
+        for value in self.fields.values() {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl Trace for LoxClass {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(superclass) = self.superclass {
+            tracer.mark(superclass);
+        }
+        for method in self.methods.values() {
+            method.trace(tracer);
+        }
+    }
+}
@@ -1,22 +1,72 @@
+use crate::collections::{ListMethod, LoxMap, MapMethod, list_index, map_key};
+use crate::error::{LoxError, StackFrame};
 use crate::expressions::{
-    Assign, Binary, Call, Expr, Get, Grouping, Literal, Logical, Set, This, Unary, Variable,
-    VisitableE, VisitorE,
+    Assign, Binary, Call, Expr, Get, Grouping, Index, Interpolation, Lambda, List, Literal,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable, VisitableE, VisitorE,
 };
+use crate::gc::{Gc, Heap, Managed, Trace};
 use crate::scanner::{Token, TokenType};
 use crate::statements::{
-    Block, Class, Func, IfStmt, ReturnStmt, Stmt, Var, VisitableS, VisitorS, WhileStmt,
+    Block, BreakStmt, Class, ContinueStmt, Func, IfStmt, ReturnStmt, Stmt, ThrowStmt, TryStmt, Var,
+    VisitableS, VisitorS, WhileStmt,
+};
+use crate::types::{
+    Callable, ClassRef, Function, InstanceRef, LoxClass, LoxInstance, NativeFunc, Object,
+    check_arity,
 };
-use crate::types::{Function, LoxClass, LoxInstance, NativeFunc, Object};
 use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
 
 pub struct RuntimeError {
     message: String,
-    token: Token,
+    token: Box<Token>,
+    // Filled in as the error leaves the innermost function call.
+    trace: Vec<StackFrame>,
+    // The value given to `throw`, if the error came from one.
+    value: Option<Box<Object>>,
 }
 
 impl RuntimeError {
     pub fn new(message: String, token: Token) -> Self {
-        Self { message, token }
+        Self {
+            message,
+            token: Box::new(token),
+            trace: Vec::new(),
+            value: None,
+        }
+    }
+
+    /// An error for a `throw` of `value`. `message` is the message of an
+    /// `Error` object, which is reported as it is.
+    pub fn thrown(value: Object, message: Option<String>, token: Token) -> Self {
+        let message = message.unwrap_or_else(|| format!("Uncaught exception: {}", value));
+
+        Self {
+            message,
+            token: Box::new(token),
+            trace: Vec::new(),
+            value: Some(Box::new(value)),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    /// Attaches the backtrace of an error raised outside the interpreter,
+    /// e.g. by the bytecode VM.
+    pub fn with_trace(mut self, trace: Vec<StackFrame>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// The calls that were active when the error happened, innermost first.
+    /// Empty for errors raised outside of any function.
+    pub fn trace(&self) -> &[StackFrame] {
+        &self.trace
     }
 }
 
@@ -30,45 +80,48 @@ impl fmt::Display for RuntimeError {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Environment {
-    pub values: HashMap<String, Object>,
-    pub enclosing: Option<Rc<RefCell<Environment>>>,
-}
+/// The most function calls that can be active at once. Both backends stop
+/// with a stack overflow error past it.
+pub const MAX_CALL_DEPTH: usize = 10_000;
 
-fn ancestor(mut env: Rc<RefCell<Environment>>, distance: usize) -> Rc<RefCell<Environment>> {
-    for _ in 0..distance {
-        let new_env = env.borrow().enclosing.clone().unwrap();
-        env = new_env;
-    }
-    env
+/// A function call the interpreter is in the middle of.
+struct CallFrame {
+    function: String,
+    // The line of the call expression, where execution resumes in the caller.
+    line: u64,
 }
 
-pub fn get_at(env: Rc<RefCell<Environment>>, distance: usize, name: String) -> Option<Object> {
-    ancestor(env, distance).borrow().values.get(&name).cloned()
+/// How a statement left the normal flow of execution.
+#[derive(Debug)]
+pub enum ControlFlow {
+    Return(Object),
+    Break,
+    Continue,
 }
 
-fn assign_at(env: Rc<RefCell<Environment>>, distance: usize, name: Token, value: Object) {
-    ancestor(env, distance)
-        .borrow_mut()
-        .values
-        .insert(name.lexeme.unwrap(), value);
+/// The local variables of one scope, in the slots the `Resolver` gave
+/// them. Globals are kept by name in the `Interpreter` instead.
+#[derive(Debug)]
+pub struct Environment {
+    pub slots: Vec<Object>,
+    pub enclosing: Option<Gc<Environment>>,
 }
 
 impl Environment {
-    pub fn new(enclosing: Option<Rc<RefCell<Environment>>>) -> Self {
+    pub fn new(enclosing: Option<Gc<Environment>>) -> Self {
         Self {
-            values: HashMap::new(),
+            slots: Vec::new(),
             enclosing,
         }
     }
 
-    pub fn set(&mut self, name: String, value: Object) {
-        self.values.insert(name, value);
+    /// A scope whose first slots are already filled, e.g. with arguments.
+    pub fn with_slots(slots: Vec<Object>, enclosing: Option<Gc<Environment>>) -> Self {
+        Self { slots, enclosing }
     }
 
     //fn get(&self, name: Token) -> Result<Object, RuntimeError> {
-    //    match self.values.get(&name.clone().lexeme.unwrap()) {
+    //    match self.values.get(&name.lexeme.clone()) {
     //        Some(v) => Ok(v.clone()),
     //        _ => match &self.enclosing {
     //            Some(e) => e.borrow().get(name),
@@ -79,185 +132,453 @@ impl Environment {
     //        },
     //    }
     //}
+}
 
-    fn assign(&mut self, name: Token, value: Object) -> Result<Object, RuntimeError> {
-        let key = name.lexeme.clone().unwrap();
-        match self.values.get(&key) {
-            Some(_) => {
-                self.set(key, value.clone());
-                return Ok(value);
-            }
-            _ => match &self.enclosing {
-                Some(e) => e.borrow_mut().assign(name, value),
-                _ => Err(RuntimeError {
-                    message: "Undefined variable.".to_string(),
-                    token: name,
-                }),
-            },
-        }
-    }
+/// Where the `Resolver` found a local variable: how many scopes out from
+/// where it is used, and its slot in that scope.
+#[derive(Clone, Copy, Debug)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
 }
 
 pub struct Interpreter {
-    globals: Rc<RefCell<Environment>>,
-    pub env: Rc<RefCell<Environment>>,
-    locals: HashMap<usize, usize>,
+    pub(crate) heap: Heap,
+    globals: HashMap<String, Object>,
+    // The innermost local scope, or `None` at the top level.
+    pub env: Option<Gc<Environment>>,
+    // The environments that `env` will return to as blocks and calls end.
+    scopes: Vec<Option<Gc<Environment>>>,
+    // Values the current statements are still working with, kept alive
+    // through collections until the statement that produced them ends.
+    temps: Vec<Object>,
+    locals: HashMap<usize, Slot>,
+    frames: Vec<CallFrame>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        let global = Rc::new(RefCell::new(Environment {
-            values: HashMap::from([
+        Self {
+            heap: Heap::new(),
+            globals: HashMap::from([
                 ("input".to_string(), Object::NativeFunc(NativeFunc::INPUT)),
                 (
                     "println".to_string(),
                     Object::NativeFunc(NativeFunc::PRINTLN),
                 ),
                 ("print".to_string(), Object::NativeFunc(NativeFunc::PRINT)),
+                (
+                    "gcStats".to_string(),
+                    Object::NativeFunc(NativeFunc::GCSTATS),
+                ),
             ]),
-            enclosing: None,
-        }));
-
-        Self {
-            env: global.clone(),
-            globals: global,
+            env: None,
+            scopes: Vec::new(),
+            temps: Vec::new(),
             locals: HashMap::new(),
+            frames: Vec::new(),
         }
     }
 
-    pub fn evaluate(&mut self, expr: &mut Expr) -> Result<Object, RuntimeError> {
-        return expr.accept(self);
+    /// An interpreter that collects garbage on every allocation, to make any
+    /// object that isn't reachable from the roots fail fast.
+    pub fn with_gc_stress() -> Self {
+        let mut interpreter = Self::new();
+        interpreter.heap.set_stress(true);
+        interpreter
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Object, RuntimeError> {
+        let value = expr.accept(self)?;
+        self.root(value.clone());
+        Ok(value)
     }
 
-    pub fn execute(&mut self, stmt: &mut Stmt) -> Result<Option<Object>, RuntimeError> {
-        stmt.accept(self)
+    /// Evaluates an expression on its own, outside of any statement, as the
+    /// REPL does for a line holding a single expression.
+    pub fn interpret_expr(&mut self, expr: &Expr) -> Result<Object, RuntimeError> {
+        let mark = self.temps.len();
+        let result = self.evaluate(expr);
+        self.temps.truncate(mark);
+        result
     }
 
-    pub fn resolve(&mut self, value: Expr, depth: usize) {
-        self.locals.insert(value.get_id(), depth);
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<Option<ControlFlow>, RuntimeError> {
+        let mark = self.temps.len();
+        let result = stmt.accept(self);
+        self.temps.truncate(mark);
+        result
+    }
+
+    /// Keeps `value` alive until the current statement ends.
+    fn root(&mut self, value: Object) {
+        match value {
+            Object::Num(_) | Object::Str(_) | Object::Bool(_) | Object::None => (),
+            _ => self.temps.push(value),
+        }
+    }
+
+    /// Moves `object` onto the heap, first collecting garbage if it is due.
+    /// Everything reachable from the roots or from `object` survives.
+    pub(crate) fn alloc<T: Managed>(&mut self, object: T) -> Gc<T> {
+        let gc = self.heap.insert(object);
+        if self.heap.should_collect() {
+            let Self {
+                heap,
+                globals,
+                env,
+                scopes,
+                temps,
+                ..
+            } = self;
+            heap.collect(|tracer| {
+                tracer.mark(gc);
+                for scope in scopes.iter().chain([&*env]).flatten() {
+                    tracer.mark(*scope);
+                }
+                for value in globals.values().chain(temps.iter()) {
+                    value.trace(tracer);
+                }
+            });
+        }
+
+        gc
+    }
+
+    fn ancestor(&self, distance: usize) -> Gc<Environment> {
+        let mut env = self.env.unwrap();
+        for _ in 0..distance {
+            env = self.heap.get(env).enclosing.unwrap();
+        }
+        env
+    }
+
+    fn get_at(&self, slot: Slot) -> Object {
+        let env = self.ancestor(slot.depth);
+        self.heap.get(env).slots[slot.index].clone()
+    }
+
+    fn assign_at(&mut self, slot: Slot, value: Object) {
+        let env = self.ancestor(slot.depth);
+        self.heap.get_mut(env).slots[slot.index] = value;
+    }
+
+    /// Defines `name` in the current scope. Locals are declared in the order
+    /// the `Resolver` numbered them, so each one takes the next slot.
+    fn define(&mut self, name: &str, value: Object) {
+        match self.env {
+            Some(env) => self.heap.get_mut(env).slots.push(value),
+            None => {
+                self.globals.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// A new environment nested in the current one.
+    fn new_scope(&mut self) -> Gc<Environment> {
+        self.alloc(Environment::new(self.env))
+    }
+
+    /// The value a `catch` clause binds an error to: the thrown value, or an
+    /// `Error` instance for errors raised by the interpreter itself.
+    fn error_value(&mut self, error: RuntimeError) -> Object {
+        if let Some(value) = error.value {
+            return *value;
+        }
+
+        let class = ClassRef {
+            name: "Error".into(),
+            gc: self.alloc(LoxClass::new(None, HashMap::new())),
+        };
+        let mut instance = LoxInstance::new(class.clone());
+        instance.fields.extend([
+            ("message".to_string(), Object::Str(error.message)),
+            ("line".to_string(), Object::Num(error.token.line as f64)),
+        ]);
+
+        Object::ClassInstance(InstanceRef {
+            class_name: class.name,
+            gc: self.alloc(instance),
+        })
+    }
+
+    fn get_field(&mut self, instance: InstanceRef, name: &Token) -> Result<Object, RuntimeError> {
+        let object = self.heap.get(instance.gc);
+        if let Some(v) = object.fields.get(&name.lexeme) {
+            return Ok(v.clone());
+        }
+
+        let class = self.heap.get(object.klass.gc);
+        match class.find_method(&self.heap, &name.lexeme) {
+            Some(m) => Ok(Object::Func(Rc::new(m.bind(self, instance)))),
+            _ => Err(RuntimeError::new(
+                format!("Undefined property {}", name.lexeme.clone()),
+                name.clone(),
+            )),
+        }
+    }
+
+    pub fn resolve(&mut self, id: usize, slot: Slot) {
+        self.locals.insert(id, slot);
     }
 
     pub fn execute_block(
         &mut self,
-        stmts: &mut Vec<Stmt>,
-        env: Rc<RefCell<Environment>>,
-    ) -> Result<Option<Object>, RuntimeError> {
-        let prev = self.env.clone();
-
-        self.env = env.clone();
-        for stmt in stmts.iter_mut() {
-            if let Some(r) = self.execute(stmt)? {
-                self.env = prev;
-                return Ok(Some(r));
+        stmts: &[Stmt],
+        env: Gc<Environment>,
+    ) -> Result<Option<ControlFlow>, RuntimeError> {
+        let prev = self.env.replace(env);
+        self.scopes.push(prev);
+
+        for stmt in stmts.iter() {
+            match self.execute(stmt) {
+                Ok(None) => (),
+                result => {
+                    // Restore the scope on errors too, since they may be caught.
+                    self.env = self.scopes.pop().unwrap();
+                    return result;
+                }
             }
         }
 
-        self.env = prev;
+        self.env = self.scopes.pop().unwrap();
 
         Ok(None)
     }
 
-    pub fn interpret(&mut self, mut stmts: Vec<Stmt>) {
-        for stmt in stmts.iter_mut() {
-            match self.execute(stmt) {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    break;
-                }
+    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), LoxError> {
+        for stmt in stmts.iter() {
+            self.execute(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Names of everything currently defined in the global environment.
+    pub fn global_names(&self) -> Vec<String> {
+        self.globals.keys().cloned().collect()
+    }
+
+    /// Calls `function`, keeping a frame for it on the call stack so that an
+    /// error escaping the call can report how it got there.
+    fn call_function(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Object>,
+        paren: &Token,
+    ) -> Result<Object, RuntimeError> {
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(
+                "Stack overflow.".to_string(),
+                paren.clone(),
+            ));
+        }
+
+        self.frames.push(CallFrame {
+            function: function.qualified_name(),
+            line: paren.line,
+        });
+
+        let result = function.call(self, arguments).map_err(|mut e| {
+            if e.trace.is_empty() {
+                e.trace = self.backtrace(e.token.line);
             }
+            e
+        });
+
+        self.frames.pop();
+        result
+    }
+
+    /// Builds the backtrace for an error on `line` in the innermost frame.
+    fn backtrace(&self, mut line: u64) -> Vec<StackFrame> {
+        let mut trace = Vec::new();
+        for frame in self.frames.iter().rev() {
+            trace.push(StackFrame {
+                function: format!("{}()", frame.function),
+                line,
+            });
+            line = frame.line;
         }
+        trace.push(StackFrame {
+            function: "script".to_string(),
+            line,
+        });
+
+        trace
     }
 
-    fn lookup_variable(&mut self, name: Token, expr: Expr) -> Option<Object> {
-        if let Some(d) = self.locals.get(&expr.get_id()) {
-            return get_at(self.env.clone(), *d, name.lexeme.unwrap());
-        } else {
-            return self
-                .globals
-                .borrow()
-                .values
-                .get(&name.lexeme.clone().unwrap())
-                .map(|x| x.clone());
+    /// Looks up the variable `name` used by the expression with id `id`.
+    fn lookup_variable(&self, name: &Token, id: usize) -> Option<Object> {
+        match self.locals.get(&id) {
+            Some(slot) => Some(self.get_at(*slot)),
+            None => self.globals.get(&name.lexeme).cloned(),
         }
     }
 }
 
-impl VisitorS<Result<Option<Object>, RuntimeError>> for Interpreter {
-    fn visit_class_stmt(&mut self, stmt: &mut Class) -> Result<Option<Object>, RuntimeError> {
-        self.env
-            .borrow_mut()
-            .set(stmt.name.lexeme.clone().unwrap(), Object::None);
+impl VisitorS<Result<Option<ControlFlow>, RuntimeError>> for Interpreter {
+    fn visit_class_stmt(&mut self, stmt: &Class) -> Result<Option<ControlFlow>, RuntimeError> {
+        let mut superclass = None;
+        if let Some(s) = &stmt.superclass {
+            match self.visit_variable(s)? {
+                Object::Class(c) => superclass = Some(c),
+                _ => {
+                    return Err(RuntimeError::new(
+                        "Superclass must be a class.".to_string(),
+                        s.name.clone(),
+                    ));
+                }
+            }
+        }
+
+        let enclosing = self.env;
+        if let Some(s) = &superclass {
+            let env = Environment::with_slots(vec![Object::Class(s.clone())], self.env);
+            self.env = Some(self.alloc(env));
+        }
 
         let mut methods = HashMap::new();
-        for method in stmt.methods.clone() {
-            let name = method.name.lexeme.clone().unwrap();
+        for method in &stmt.methods {
+            let name = method.name.lexeme.clone();
             methods.insert(
                 name.clone(),
-                Function {
-                    name: method.name.lexeme.unwrap(),
-                    is_init: name.clone() == "init".to_string(),
-                    body: method.body,
-                    params: method.params,
-                    closure: self.env.clone(),
-                },
+                Rc::new(Function {
+                    name: name.clone(),
+                    is_init: name == "init",
+                    body: method.body.clone(),
+                    params: method.params.clone(),
+                    closure: self.env,
+                    bound: None,
+                }),
             );
         }
 
-        let klass = LoxClass::new(stmt.name.lexeme.clone().unwrap(), methods);
-        self.env
-            .borrow_mut()
-            .assign(stmt.name.clone(), Object::Class(klass))?;
+        let klass = LoxClass::new(superclass.map(|s| s.gc), methods);
+        let klass = ClassRef {
+            name: stmt.name.lexeme.as_str().into(),
+            gc: self.alloc(klass),
+        };
+
+        self.env = enclosing;
+        self.define(&stmt.name.lexeme, Object::Class(klass));
 
         Ok(None)
     }
 
-    fn visit_return_stmt(&mut self, stmt: &mut ReturnStmt) -> Result<Option<Object>, RuntimeError> {
+    fn visit_return_stmt(
+        &mut self,
+        stmt: &ReturnStmt,
+    ) -> Result<Option<ControlFlow>, RuntimeError> {
         let mut value = Object::None;
-        if let Some(e) = &mut stmt.value {
+        if let Some(e) = &stmt.value {
             value = self.evaluate(e)?;
         }
 
-        Ok(Some(value))
+        Ok(Some(ControlFlow::Return(value)))
     }
-    fn visit_while_stmt(&mut self, stmt: &mut WhileStmt) -> Result<Option<Object>, RuntimeError> {
-        while self.evaluate(&mut stmt.condition)?.is_truthy() {
-            if let Some(r) = self.execute(&mut stmt.body)? {
+
+    fn visit_break_stmt(&mut self, _: &BreakStmt) -> Result<Option<ControlFlow>, RuntimeError> {
+        Ok(Some(ControlFlow::Break))
+    }
+
+    fn visit_continue_stmt(
+        &mut self,
+        _: &ContinueStmt,
+    ) -> Result<Option<ControlFlow>, RuntimeError> {
+        Ok(Some(ControlFlow::Continue))
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &ThrowStmt) -> Result<Option<ControlFlow>, RuntimeError> {
+        let value = self.evaluate(&stmt.value)?;
+        let message = match &value {
+            Object::ClassInstance(i) => self.heap.get(i.gc).error_message(),
+            _ => None,
+        };
+        Err(RuntimeError::thrown(value, message, stmt.keyword.clone()))
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> Result<Option<ControlFlow>, RuntimeError> {
+        let env = self.new_scope();
+        let mut result = self.execute_block(&stmt.body, env);
+
+        if let Some(catch) = &stmt.catch
+            && let Err(e) = result
+        {
+            let value = self.error_value(e);
+            let env = self.alloc(Environment::with_slots(vec![value], self.env));
+            result = self.execute_block(&catch.body, env);
+        }
+
+        if let Some(finally) = &stmt.finally {
+            // Whatever is returned or thrown must survive the `finally`.
+            match &result {
+                Ok(Some(ControlFlow::Return(value))) => self.root(value.clone()),
+                Err(RuntimeError {
+                    value: Some(value), ..
+                }) => self.root(*value.clone()),
+                _ => (),
+            }
+
+            let env = self.new_scope();
+            // A `finally` that returns, breaks or throws overrides the outcome
+            // of the rest of the statement.
+            if let Some(r) = self.execute_block(finally, env)? {
                 return Ok(Some(r));
             }
         }
 
+        result
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Result<Option<ControlFlow>, RuntimeError> {
+        let mark = self.temps.len();
+        while self.evaluate(&stmt.condition)?.is_truthy() {
+            match self.execute(&stmt.body)? {
+                Some(ControlFlow::Break) => break,
+                Some(ControlFlow::Return(r)) => return Ok(Some(ControlFlow::Return(r))),
+                Some(ControlFlow::Continue) | None => (),
+            }
+
+            if let Some(i) = &stmt.increment {
+                self.evaluate(i)?;
+            }
+            self.temps.truncate(mark);
+        }
+
         Ok(None)
     }
 
-    fn visit_var_stmt(&mut self, stmt: &mut Var) -> Result<Option<Object>, RuntimeError> {
-        let value = match stmt.initializer.clone() {
-            Some(ref mut e) => self.evaluate(e)?,
+    fn visit_var_stmt(&mut self, stmt: &Var) -> Result<Option<ControlFlow>, RuntimeError> {
+        let value = match &stmt.initializer {
+            Some(e) => self.evaluate(e)?,
             _ => Object::None,
         };
-        self.env
-            .borrow_mut()
-            .set(stmt.token.clone().lexeme.unwrap(), value);
+        self.define(&stmt.token.lexeme, value);
         Ok(None)
     }
 
-    fn visit_expr_stmt(&mut self, expr: &mut Expr) -> Result<Option<Object>, RuntimeError> {
-        self.evaluate(&mut expr.clone())?;
+    fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<Option<ControlFlow>, RuntimeError> {
+        self.evaluate(expr)?;
         Ok(None)
     }
 
-    fn visit_block_stmt(&mut self, block: &mut Block) -> Result<Option<Object>, RuntimeError> {
-        self.execute_block(
-            &mut block.stmts,
-            Rc::new(RefCell::new(Environment::new(Some(self.env.clone())))),
-        )
+    fn visit_block_stmt(&mut self, block: &Block) -> Result<Option<ControlFlow>, RuntimeError> {
+        let env = self.new_scope();
+        self.execute_block(&block.stmts, env)
     }
 
-    fn visit_if_stmt(&mut self, stmt: &mut IfStmt) -> Result<Option<Object>, RuntimeError> {
-        if self.evaluate(&mut stmt.condition)?.is_truthy() {
-            return self.execute(&mut stmt.then_block);
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<Option<ControlFlow>, RuntimeError> {
+        if self.evaluate(&stmt.condition)?.is_truthy() {
+            return self.execute(&stmt.then_block);
         } else {
-            if let Some(s) = &mut stmt.else_block {
+            if let Some(s) = &stmt.else_block {
                 return self.execute(s);
             }
         }
@@ -265,28 +586,57 @@ impl VisitorS<Result<Option<Object>, RuntimeError>> for Interpreter {
         Ok(None)
     }
 
-    fn visit_func_stmt(&mut self, stmt: &mut Func) -> Result<Option<Object>, RuntimeError> {
-        let name = stmt.name.clone().lexeme.unwrap();
+    fn visit_func_stmt(&mut self, stmt: &Func) -> Result<Option<ControlFlow>, RuntimeError> {
+        let name = stmt.name.lexeme.clone();
 
         let function = Function {
-            name: stmt.name.clone().lexeme.unwrap(),
+            name: stmt.name.lexeme.clone(),
             is_init: false,
             body: stmt.body.clone(),
             params: stmt.params.clone(),
-            closure: self.env.clone(),
+            closure: self.env,
+            bound: None,
         };
 
-        self.env
-            .borrow_mut()
-            .set(name.clone(), Object::Func(function));
+        self.define(&name, Object::Func(Rc::new(function)));
 
         Ok(None)
     }
 }
 
 impl VisitorE<Result<Object, RuntimeError>> for Interpreter {
+    fn visit_super(&mut self, expr: &Super) -> Result<Object, RuntimeError> {
+        // `super` and `this` are each alone in their scope.
+        let slot = *self.locals.get(&expr.id).unwrap();
+        let superclass = self.get_at(slot);
+        let object = self.get_at(Slot {
+            depth: slot.depth - 1,
+            index: 0,
+        });
+
+        match (superclass, object) {
+            (Object::Class(c), Object::ClassInstance(i)) => {
+                match self
+                    .heap
+                    .get(c.gc)
+                    .find_method(&self.heap, &expr.method.lexeme)
+                {
+                    Some(m) => Ok(Object::Func(Rc::new(m.bind(self, i)))),
+                    _ => Err(RuntimeError::new(
+                        format!("Undefined property {}", expr.method.lexeme.clone()),
+                        expr.method.clone(),
+                    )),
+                }
+            }
+            _ => Err(RuntimeError::new(
+                "Undeclared variable super".to_string(),
+                expr.keyword.clone(),
+            )),
+        }
+    }
+
     fn visit_this(&mut self, expr: &This) -> Result<Object, RuntimeError> {
-        if let Some(o) = self.lookup_variable(expr.keyword.clone(), Expr::This(expr.clone())) {
+        if let Some(o) = self.lookup_variable(&expr.keyword, expr.id) {
             Ok(o)
         } else {
             Err(RuntimeError::new(
@@ -297,233 +647,344 @@ impl VisitorE<Result<Object, RuntimeError>> for Interpreter {
     }
 
     fn visit_set(&mut self, expr: &Set) -> Result<Object, RuntimeError> {
-        let mut left = self.evaluate(&mut expr.expr.clone())?;
-        if let Object::ClassInstance(i) = &mut left {
-            return i.set(
-                expr.name.lexeme.clone().unwrap(),
-                self.evaluate(&mut expr.value.clone())?,
-            );
+        let left = self.evaluate(&expr.expr)?;
+        if let Object::ClassInstance(i) = &left {
+            let value = self.evaluate(&expr.value)?;
+            self.heap.get_mut(i.gc).set(expr.name.lexeme.clone(), value)
         } else {
-            return Err(RuntimeError::new(
+            Err(RuntimeError::new(
                 "Only instances have properties.".to_string(),
                 expr.name.clone(),
-            ));
+            ))
         }
     }
 
     fn visit_get(&mut self, expr: &Get) -> Result<Object, RuntimeError> {
-        let left = self.evaluate(&mut expr.expr.clone())?;
+        let left = self.evaluate(&expr.expr)?;
         if let Object::ClassInstance(i) = left {
-            return i.get(expr.name.clone());
+            self.get_field(i, &expr.name)
+        } else if let Object::List(l) = left {
+            match ListMethod::from_name(&expr.name.lexeme.clone()) {
+                Some(m) => Ok(Object::NativeFunc(NativeFunc::LIST(l, m))),
+                _ => Err(RuntimeError::new(
+                    format!("Undefined property {}", expr.name.lexeme.clone()),
+                    expr.name.clone(),
+                )),
+            }
+        } else if let Object::Map(m) = left {
+            match MapMethod::from_name(&expr.name.lexeme.clone()) {
+                Some(method) => Ok(Object::NativeFunc(NativeFunc::MAP(m, method))),
+                _ => Err(RuntimeError::new(
+                    format!("Undefined property {}", expr.name.lexeme.clone()),
+                    expr.name.clone(),
+                )),
+            }
         } else {
-            return Err(RuntimeError::new(
+            Err(RuntimeError::new(
                 "Only instances have properties.".to_string(),
                 expr.name.clone(),
-            ));
+            ))
+        }
+    }
+
+    fn visit_lambda(&mut self, expr: &Lambda) -> Result<Object, RuntimeError> {
+        Ok(Object::Func(Rc::new(Function {
+            name: "lambda".to_string(),
+            is_init: false,
+            body: expr.body.clone(),
+            params: expr.params.clone(),
+            closure: self.env,
+            bound: None,
+        })))
+    }
+
+    fn visit_interpolation(&mut self, expr: &Interpolation) -> Result<Object, RuntimeError> {
+        let mut result = String::new();
+        for part in expr.parts.iter() {
+            result.push_str(&self.evaluate(part)?.to_string());
+        }
+
+        Ok(Object::Str(result))
+    }
+
+    fn visit_list(&mut self, expr: &List) -> Result<Object, RuntimeError> {
+        let mut elements = Vec::new();
+        for element in expr.elements.iter() {
+            elements.push(self.evaluate(element)?);
+        }
+
+        Ok(Object::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_map(&mut self, expr: &Map) -> Result<Object, RuntimeError> {
+        let mut map = LoxMap::new();
+        for (key, value) in expr.entries.iter() {
+            let key = map_key(&self.evaluate(key)?, &expr.brace)?;
+            map.insert(key, self.evaluate(value)?);
+        }
+
+        Ok(Object::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_index(&mut self, expr: &Index) -> Result<Object, RuntimeError> {
+        let left = self.evaluate(&expr.expr)?;
+        let index = self.evaluate(&expr.index)?;
+
+        if let Object::List(l) = left {
+            let l = l.borrow();
+            let i = list_index(&index, l.len(), &expr.bracket)?;
+            Ok(l[i].clone())
+        } else if let Object::Map(m) = left {
+            return match m.borrow().get(&map_key(&index, &expr.bracket)?) {
+                Some(v) => Ok(v),
+                _ => Err(RuntimeError::new(
+                    format!("Undefined key {}.", index),
+                    expr.bracket.clone(),
+                )),
+            };
+        } else {
+            Err(RuntimeError::new(
+                "Only lists and maps can be indexed.".to_string(),
+                expr.bracket.clone(),
+            ))
+        }
+    }
+
+    fn visit_set_index(&mut self, expr: &SetIndex) -> Result<Object, RuntimeError> {
+        let left = self.evaluate(&expr.expr)?;
+        let index = self.evaluate(&expr.index)?;
+        let value = self.evaluate(&expr.value)?;
+
+        if let Object::List(l) = left {
+            let mut l = l.borrow_mut();
+            let i = list_index(&index, l.len(), &expr.bracket)?;
+            l[i] = value.clone();
+            Ok(value)
+        } else if let Object::Map(m) = left {
+            m.borrow_mut()
+                .insert(map_key(&index, &expr.bracket)?, value.clone());
+            Ok(value)
+        } else {
+            Err(RuntimeError::new(
+                "Only lists and maps can be indexed.".to_string(),
+                expr.bracket.clone(),
+            ))
         }
     }
 
     fn visit_call(&mut self, expr: &Call) -> Result<Object, RuntimeError> {
-        let mut calle = self.evaluate(&mut expr.calle.clone())?;
-        match &mut calle {
+        let calle = self.evaluate(&expr.calle)?;
+        match &calle {
             Object::Func(f) => {
                 let mut args = Vec::new();
-                for arg in expr.arguments.clone().iter_mut() {
+                for arg in expr.arguments.iter() {
                     args.push(self.evaluate(arg)?);
                 }
-                return f.call(self, args);
+
+                f.check_arity(args.len(), &expr.paren)?;
+                self.call_function(f, args, &expr.paren)
             }
             Object::NativeFunc(f) => {
                 let mut args = Vec::new();
-                for arg in expr.arguments.clone().iter_mut() {
+                for arg in expr.arguments.iter() {
                     args.push(self.evaluate(arg)?);
                 }
 
-                return f.call(expr.paren.clone(), args);
+                f.check_arity(args.len(), &expr.paren)?;
+                f.call(self, expr.paren.clone(), args)
             }
             Object::Class(c) => {
                 let mut args = Vec::new();
-                for arg in expr.arguments.clone().iter_mut() {
+                for arg in expr.arguments.iter() {
                     args.push(self.evaluate(arg)?);
                 }
 
-                let instance = LoxInstance::new(c.clone());
-                if let Some(init_method) = c.find_method("init".to_string()) {
-                    init_method.bind(instance.clone()).call(self, args)?;
+                // Constructing an instance takes the arguments of `init`, if any.
+                let init = self.heap.get(c.gc).find_method(&self.heap, "init");
+                match &init {
+                    Some(init) => init.check_arity(args.len(), &expr.paren)?,
+                    _ => check_arity(0..=0, args.len(), &expr.paren)?,
                 }
 
-                return Ok(Object::ClassInstance(instance));
-            }
-            _ => {
-                return Err(RuntimeError {
-                    message: "Can only call functions and classes.".to_owned(),
-                    token: expr.paren.clone(),
-                });
+                let instance = InstanceRef {
+                    class_name: c.name.clone(),
+                    gc: self.alloc(LoxInstance::new(c.clone())),
+                };
+                self.root(Object::ClassInstance(instance.clone()));
+                if let Some(init_method) = init {
+                    let initializer = init_method.bind(self, instance.clone());
+                    self.call_function(&initializer, args, &expr.paren)?;
+                }
+
+                Ok(Object::ClassInstance(instance))
             }
+            _ => Err(RuntimeError::new(
+                "Can only call functions and classes.".to_owned(),
+                expr.paren.clone(),
+            )),
         }
     }
 
     fn visit_logical(&mut self, expr: &Logical) -> Result<Object, RuntimeError> {
-        let left = self.evaluate(&mut expr.left.clone())?;
-        let right = self.evaluate(&mut expr.right.clone())?;
+        let left = self.evaluate(&expr.left)?;
 
         match expr.operator.token_type {
             TokenType::OR => {
                 if left.is_truthy() {
                     return Ok(left);
                 }
-                Ok(right)
             }
             TokenType::AND => {
                 if !left.is_truthy() {
                     return Ok(left);
                 }
-                Ok(right)
             }
             _ => unreachable!(),
         }
+
+        self.evaluate(&expr.right)
     }
 
     fn visit_variable(&mut self, expr: &Variable) -> Result<Object, RuntimeError> {
-        match self.lookup_variable(expr.name.clone(), Expr::Var(expr.clone())) {
+        match self.lookup_variable(&expr.name, expr.id) {
             Some(o) => Ok(o),
-            _ => Err(RuntimeError {
-                message: "Undefined variable.".to_string(),
-                token: expr.name.clone(),
-            }),
+            _ => Err(RuntimeError::new(
+                "Undefined variable.".to_string(),
+                expr.name.clone(),
+            )),
         }
     }
 
     fn visit_binary(&mut self, expr: &Binary) -> Result<Object, RuntimeError> {
-        let left = self.evaluate(&mut expr.left.clone())?;
-        let right = self.evaluate(&mut expr.right.clone())?;
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
 
         match expr.operator.token_type {
             TokenType::MINUS => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Num(l - r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Num(l - r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numbers.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::PLUS => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Num(l + r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Num(l + r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
                 Object::Str(l) => {
-                        let mut s = l.clone();
-                        s.push_str(&right.to_string());
-                        return Ok(Object::Str(s));
-                },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numbers or two strings.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                    let mut s = l.clone();
+                    s.push_str(&right.to_string());
+                    Ok(Object::Str(s))
+                }
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers or two strings.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::STAR => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Num(l * r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Num(l * r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numbers.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::SLASH => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Num(l / r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Num(l / r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numbers.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::GREATER => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Bool(l > r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Bool(l > r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numbers.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::LESS => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Bool(l < r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Bool(l < r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numberss.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::GREATEREQUAL => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Bool(l >= r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Bool(l >= r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numbers.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::LESSEQUAL => match left {
                 Object::Num(l) => match right {
-                    Object::Num(r) => return Ok(Object::Bool(l <= r)),
-                    _ => Err(RuntimeError {
-                        message: "operands must be two numbers.".to_string(),
-                        token: expr.operator.clone(),
-                    }),
+                    Object::Num(r) => Ok(Object::Bool(l <= r)),
+                    _ => Err(RuntimeError::new(
+                        "operands must be two numbers.".to_string(),
+                        expr.operator.clone(),
+                    )),
                 },
-                _ => Err(RuntimeError {
-                    message: "operands must be two numbers.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                _ => Err(RuntimeError::new(
+                    "operands must be two numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
-            TokenType::EQUALEQUAL => return Ok(Object::Bool(left == right)),
-            TokenType::BANGEQUAL => return Ok(Object::Bool(!(left == right))),
+            TokenType::EQUALEQUAL => Ok(Object::Bool(left == right)),
+            TokenType::BANGEQUAL => Ok(Object::Bool(!(left == right))),
             _ => unreachable!(),
         }
     }
 
     fn visit_unary(&mut self, expr: &Unary) -> Result<Object, RuntimeError> {
-        let right = self.evaluate(&mut expr.right.clone())?;
+        let right = self.evaluate(&expr.right)?;
 
         match expr.operator.token_type {
             TokenType::MINUS => match right {
-                Object::Num(n) => return Ok(Object::Num(-1. * n)),
-                _ => Err(RuntimeError {
-                    message: "operands must be numbers.".to_string(),
-                    token: expr.operator.clone(),
-                }),
+                Object::Num(n) => Ok(Object::Num(-n)),
+                _ => Err(RuntimeError::new(
+                    "operands must be numbers.".to_string(),
+                    expr.operator.clone(),
+                )),
             },
             TokenType::BANG => Ok(Object::Bool(!right.is_truthy())),
             _ => unreachable!(),
@@ -531,7 +992,7 @@ impl VisitorE<Result<Object, RuntimeError>> for Interpreter {
     }
 
     fn visit_grouping(&mut self, expr: &Grouping) -> Result<Object, RuntimeError> {
-        return self.evaluate(&mut expr.expr.clone());
+        self.evaluate(&expr.expr)
     }
 
     fn visit_literal(&mut self, expr: &Literal) -> Result<Object, RuntimeError> {
@@ -539,19 +1000,15 @@ impl VisitorE<Result<Object, RuntimeError>> for Interpreter {
     }
 
     fn visit_assign(&mut self, expr: &Assign) -> Result<Object, RuntimeError> {
-        let value = self.evaluate(&mut expr.value.clone())?;
+        let value = self.evaluate(&expr.value)?;
 
-        if let Some(d) = self.locals.get(&expr.id) {
-            assign_at(self.env.clone(), *d, expr.name.clone(), value.clone());
-            // return the value we just assigned
-            Ok(get_at(self.env.clone(), *d, expr.name.lexeme.clone().unwrap()).unwrap())
+        if let Some(slot) = self.locals.get(&expr.id).copied() {
+            self.assign_at(slot, value.clone());
         } else {
-            // assign into globals and return the assigned value
-            self.globals
-                .borrow_mut()
-                .values
-                .insert(expr.name.lexeme.clone().unwrap(), value.clone());
-            Ok(value)
+            // assigning an undefined global defines it
+            self.globals.insert(expr.name.lexeme.clone(), value.clone());
         }
+
+        Ok(value)
     }
 }
@@ -1,7 +1,13 @@
-mod expressions;
+pub mod collections;
+pub mod completion;
+pub mod diagnostic;
+pub mod error;
+pub mod expressions;
+pub mod gc;
 pub mod interpreter;
 pub mod parser;
 pub mod resolver;
 pub mod scanner;
-mod types;
-mod statements;
+pub mod statements;
+pub mod types;
+pub mod vm;
@@ -1,38 +1,133 @@
+use rlox::error::LoxError;
 use rlox::interpreter::Interpreter;
 use rlox::parser::Parser;
 use rlox::resolver::Resolver;
 use rlox::scanner::Scanner;
+use rlox::vm::{Compiler, Vm, disassemble};
 use std::env;
+use std::io::{self, IsTerminal};
+
+mod repl;
+
+/// What to do with a script.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+enum Backend {
+    TREEWALK,
+    VM,
+    /// Print the compiled bytecode instead of running it.
+    DISASM,
+}
+
+#[derive(Clone, Copy)]
+struct Options {
+    backend: Backend,
+    // Print the bytecode before running it on the VM.
+    dump_bytecode: bool,
+    // Print the VM's stack and each instruction as it runs.
+    trace: bool,
+    // Collect garbage on every allocation in the tree-walker.
+    gc_stress: bool,
+}
+
+const USAGE: &str = "Usage: rlox [--vm] [--dump-bytecode] [--trace] [--gc-stress] [script]
+       rlox disasm [script]";
+
+// The tree-walker takes several native frames for each Lox call, so it runs
+// on a thread with room for `MAX_CALL_DEPTH` calls of them.
+const STACK_SIZE: usize = 1 << 30;
 
 fn main() {
-    if env::args().len() > 2 {
-        println!("Usage: rlox [script]");
-        return;
+    let thread = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(start)
+        .unwrap();
+    if thread.join().is_err() {
+        std::process::exit(101);
+    }
+}
+
+fn start() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut options = Options {
+        backend: Backend::TREEWALK,
+        dump_bytecode: false,
+        trace: false,
+        gc_stress: false,
+    };
+    if args.first().is_some_and(|a| a == "disasm") {
+        options.backend = Backend::DISASM;
+        args.remove(0);
     }
 
-    let file_name = env::args().nth(1).unwrap();
-    run_file(&file_name);
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--vm" => options.backend = Backend::VM,
+            "--dump-bytecode" => options.dump_bytecode = true,
+            "--trace" => options.trace = true,
+            "--gc-stress" => options.gc_stress = true,
+            _ => paths.push(arg),
+        }
+    }
+    // Both only make sense for the VM, so they select it.
+    if (options.dump_bytecode || options.trace) && options.backend == Backend::TREEWALK {
+        options.backend = Backend::VM;
+    }
+
+    match (paths.as_slice(), options.backend) {
+        ([], Backend::TREEWALK) => repl::run_prompt(),
+        ([path], _) => run_file(path, options),
+        _ => println!("{}", USAGE),
+    }
 }
 
-fn run(source: String) {
-    let tokens = Scanner::new(source.chars().peekable()).scan_tokens();
-    match tokens {
-        Some(t) => match Parser::new(t).parse() {
-            Ok(mut e) => {
-                let mut interpreter = Interpreter::new();
-                let mut resolver = Resolver::new(Vec::new(), &mut interpreter);
-                match resolver.resolve_stmts(&mut e) {
-                    Some(_) => interpreter.interpret(e),
-                    _ => std::process::exit(67),
-                }
-            }
-            _ => std::process::exit(67),
-        },
-        None => std::process::exit(67),
+fn run(source: &str, options: Options) -> Result<(), Vec<LoxError>> {
+    let tokens = Scanner::new(source.chars().peekable()).scan_tokens()?;
+    let (stmts, errors) = Parser::new(tokens).parse();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut interpreter = if options.gc_stress {
+        Interpreter::with_gc_stress()
+    } else {
+        Interpreter::new()
+    };
+    let mut resolver = Resolver::new(Vec::new(), &mut interpreter);
+    resolver.resolve_stmts(&stmts)?;
+
+    if options.backend == Backend::TREEWALK {
+        return interpreter.interpret(stmts).map_err(|e| vec![e]);
     }
+
+    let function = Compiler::new().compile(&stmts)?;
+    if options.backend == Backend::DISASM {
+        print!("{}", disassemble(&function));
+        return Ok(());
+    }
+    if options.dump_bytecode {
+        eprintln!("{}", disassemble(&function));
+    }
+
+    let mut vm = if options.trace {
+        Vm::with_trace()
+    } else {
+        Vm::new()
+    };
+    vm.interpret(function).map_err(|e| vec![e])
 }
 
-fn run_file(path: &str) {
+fn run_file(path: &str, options: Options) {
     let source = std::fs::read_to_string(path).unwrap();
-    run(source);
+    if let Err(errors) = run(&source, options) {
+        let color = io::stderr().is_terminal();
+        for e in &errors {
+            eprintln!("{}\n", e.render(&source, path, color));
+        }
+
+        if !matches!(errors[0], LoxError::Runtime(_)) {
+            std::process::exit(67);
+        }
+    }
 }
@@ -1,36 +1,126 @@
+use crate::error::LoxError;
 use crate::expressions::{
-    Assign, Binary, Call, Expr, Get, Grouping, Literal, Logical, Set, This, Unary, Variable,
+    Assign, Binary, Call, Expr, Get, Grouping, Index, Interpolation, Lambda, List, Literal,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable,
+};
+use crate::scanner::{Span, Token, TokenType};
+use crate::statements::{
+    Block, BreakStmt, CatchClause, Class, ContinueStmt, Func, IfStmt, ReturnStmt, Stmt, ThrowStmt,
+    TryStmt, Var, WhileStmt,
 };
-use crate::scanner::{Token, TokenType};
-use crate::statements::{Block, Class, Func, IfStmt, ReturnStmt, Stmt, Var, WhileStmt};
 use crate::types::Object;
+use std::rc::Rc;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     new_id: usize,
+    errors: Vec<LoxError>,
+    // How many blocks the current token is nested in.
+    blocks: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
+        Self::with_next_id(tokens, 0)
+    }
+
+    /// Creates a parser whose expression ids start at `next_id`, so that
+    /// several parses can share one `Interpreter` without their ids clashing.
+    pub fn with_next_id(tokens: Vec<Token>, next_id: usize) -> Self {
         Self {
             tokens,
             current: 0,
-            new_id: 0,
+            new_id: next_id,
+            errors: Vec::new(),
+            blocks: 0,
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, ()> {
+    pub fn next_id(&self) -> usize {
+        self.new_id
+    }
+
+    /// Parses the whole program, recovering from syntax errors so that all
+    /// of them are reported. Returns the statements that did parse together
+    /// with the errors.
+    pub fn parse(&mut self) -> (Vec<Stmt>, Vec<LoxError>) {
         let mut stmts = Vec::new();
 
         while !self.is_at_end() {
-            stmts.push(self.declaration()?);
+            if let Some(s) = self.recovering_declaration() {
+                stmts.push(s);
+            }
         }
 
-        Ok(stmts)
+        (stmts, std::mem::take(&mut self.errors))
+    }
+
+    /// Parses the tokens as one expression and nothing else, as the REPL
+    /// does for input that isn't a valid program.
+    pub fn parse_expression(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            return Err(self.error(self.peek(), "Expect end of expression."));
+        }
+        // Errors recovered from in a lambda body still spoil the expression.
+        if !self.errors.is_empty() {
+            return Err(self.errors.remove(0));
+        }
+
+        Ok(expr)
+    }
+
+    fn recovering_declaration(&mut self) -> Option<Stmt> {
+        match self.declaration() {
+            Ok(s) => Some(s),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    /// Skips tokens until the start of the next statement. Braces opened
+    /// while skipping are skipped whole, and a `}` that closes the enclosing
+    /// block is left for `block` to consume.
+    fn synchronize(&mut self) {
+        let mut depth: usize = 0;
+
+        while !self.is_at_end() {
+            match self.peek().token_type {
+                TokenType::LEFTBRACE => depth += 1,
+                TokenType::RIGHTBRACE if depth == 0 && self.blocks > 0 => return,
+                TokenType::RIGHTBRACE => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+
+            self.advance();
+            if depth > 0 {
+                continue;
+            }
+
+            if self.previous().token_type == TokenType::SEMICOLON {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::CLASS
+                | TokenType::FUN
+                | TokenType::VAR
+                | TokenType::FOR
+                | TokenType::IF
+                | TokenType::WHILE
+                | TokenType::RETURN
+                | TokenType::THROW
+                | TokenType::TRY => return,
+                _ => (),
+            }
+        }
     }
 
-    fn declaration(&mut self) -> Result<Stmt, ()> {
+    fn declaration(&mut self) -> Result<Stmt, LoxError> {
         if self.matchh(vec![TokenType::VAR]) {
             return self.var_decl();
         }
@@ -39,28 +129,51 @@ impl Parser {
             return self.class_decl();
         }
 
-        if self.matchh(vec![TokenType::FUN]) {
-            return Ok(Stmt::Func(self.function("function".to_string())?));
+        // `fun (` starts an anonymous function in an expression statement.
+        if self.check(&TokenType::FUN) && !self.check_next(&TokenType::LEFTPAREN) {
+            self.advance();
+            return Ok(Stmt::Func(self.function()?));
         }
 
         self.statement()
     }
 
-    fn class_decl(&mut self) -> Result<Stmt, ()> {
+    fn class_decl(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
+        let doc = self.previous().doc;
         let name = self.consume(&TokenType::IDENTIFIER, "Expect class name.")?;
+
+        let mut superclass = None;
+        if self.matchh(vec![TokenType::LESS]) {
+            let name = self.consume(&TokenType::IDENTIFIER, "Expect superclass name.")?;
+            superclass = Some(Variable {
+                id: self.get_new_id(),
+                span: name.span,
+                name,
+            });
+        }
+
         self.consume(&TokenType::LEFTBRACE, "Expect '{' before class body.")?;
 
         let mut methods = Vec::new();
 
         while !self.is_at_end() && !self.check(&TokenType::RIGHTBRACE) {
-            methods.push(self.function("method".to_string())?);
+            methods.push(self.function()?);
         }
 
         self.consume(&TokenType::RIGHTBRACE, "Expect '}' after class body.")?;
-        Ok(Stmt::Class(Class { name, methods }))
+        Ok(Stmt::Class(Class {
+            span: self.span_from(start),
+            name,
+            superclass,
+            methods,
+            doc,
+        }))
     }
 
-    fn var_decl(&mut self) -> Result<Stmt, ()> {
+    fn var_decl(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
+        let doc = self.previous().doc;
         let name = self.consume(&TokenType::IDENTIFIER, "Expect variable name.")?;
 
         let mut initializer = None;
@@ -70,19 +183,50 @@ impl Parser {
 
         self.consume(&TokenType::SEMICOLON, "Expect ';' after value.")?;
         Ok(Stmt::Var(Var {
+            span: self.span_from(start),
             token: name,
             initializer,
+            doc,
         }))
     }
 
-    fn statement(&mut self) -> Result<Stmt, ()> {
+    fn statement(&mut self) -> Result<Stmt, LoxError> {
         if self.matchh(vec![TokenType::RETURN]) {
             return self.return_stmt();
         }
 
+        if self.matchh(vec![TokenType::THROW]) {
+            return self.throw_stmt();
+        }
+
+        if self.matchh(vec![TokenType::TRY]) {
+            return self.try_stmt();
+        }
+
+        if self.matchh(vec![TokenType::BREAK]) {
+            let keyword = self.previous();
+            self.consume(&TokenType::SEMICOLON, "Expect ';' after 'break'.")?;
+            return Ok(Stmt::Break(BreakStmt {
+                span: self.span_from(keyword.span),
+                keyword,
+            }));
+        }
+
+        if self.matchh(vec![TokenType::CONTINUE]) {
+            let keyword = self.previous();
+            self.consume(&TokenType::SEMICOLON, "Expect ';' after 'continue'.")?;
+            return Ok(Stmt::Continue(ContinueStmt {
+                span: self.span_from(keyword.span),
+                keyword,
+            }));
+        }
+
         if self.matchh(vec![TokenType::LEFTBRACE]) {
+            let start = self.previous().span;
+            let stmts = self.block()?;
             return Ok(Stmt::Block(Block {
-                stmts: self.block()?,
+                span: self.span_from(start),
+                stmts,
             }));
         }
 
@@ -101,7 +245,54 @@ impl Parser {
         self.expr_stmt()
     }
 
-    fn return_stmt(&mut self) -> Result<Stmt, ()> {
+    fn throw_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(&TokenType::SEMICOLON, "Expect ';' after thrown value.")?;
+
+        Ok(Stmt::Throw(ThrowStmt {
+            span: self.span_from(keyword.span),
+            keyword,
+            value,
+        }))
+    }
+
+    fn try_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        self.consume(&TokenType::LEFTBRACE, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+
+        let mut catch = None;
+        if self.matchh(vec![TokenType::CATCH]) {
+            self.consume(&TokenType::LEFTPAREN, "Expect '(' after 'catch'.")?;
+            let name = self.consume(&TokenType::IDENTIFIER, "Expect error variable name.")?;
+            self.consume(&TokenType::RIGHTPAREN, "Expect ')' after error variable.")?;
+            self.consume(&TokenType::LEFTBRACE, "Expect '{' before catch body.")?;
+            catch = Some(CatchClause {
+                name,
+                body: self.block()?,
+            });
+        }
+
+        let mut finally = None;
+        if self.matchh(vec![TokenType::FINALLY]) {
+            self.consume(&TokenType::LEFTBRACE, "Expect '{' after 'finally'.")?;
+            finally = Some(self.block()?);
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(self.peek(), "Expect 'catch' or 'finally' after try block."));
+        }
+
+        Ok(Stmt::Try(TryStmt {
+            span: self.span_from(keyword.span),
+            body,
+            catch,
+            finally,
+        }))
+    }
+
+    fn return_stmt(&mut self) -> Result<Stmt, LoxError> {
         let keyword = self.previous();
         let mut value = None;
         if !self.check(&TokenType::SEMICOLON) {
@@ -110,13 +301,39 @@ impl Parser {
 
         self.consume(&TokenType::SEMICOLON, "Expect ';' after return statement.")?;
 
-        Ok(Stmt::Return(ReturnStmt { keyword, value }))
+        Ok(Stmt::Return(ReturnStmt {
+            span: self.span_from(keyword.span),
+            keyword,
+            value,
+        }))
     }
 
-    fn function(&mut self, _kind: String) -> Result<Func, ()> {
+    fn function(&mut self) -> Result<Func, LoxError> {
+        // Functions are documented before `fun`, methods before their name.
+        let keyword_doc = match self.previous().token_type {
+            TokenType::FUN => self.previous().doc,
+            _ => None,
+        };
         let name = self.consume(&TokenType::IDENTIFIER, "Expect name.")?;
+        let doc = keyword_doc.or(name.doc.clone());
         self.consume(&TokenType::LEFTPAREN, "Expect '(' after function name.")?;
+        let params = self.parameters()?;
+
+        self.consume(&TokenType::LEFTBRACE, "Expect '{' before function body.")?;
+
+        let body = self.block()?;
 
+        Ok(Func {
+            span: self.span_from(name.span),
+            name,
+            body: body.into(),
+            params,
+            doc,
+        })
+    }
+
+    /// Parses a parameter list whose `(` has been consumed, up to its `)`.
+    fn parameters(&mut self) -> Result<Rc<[Token]>, LoxError> {
         let mut params = Vec::new();
         if !self.check(&TokenType::RIGHTPAREN) {
             params.push(self.consume(&TokenType::IDENTIFIER, "Expect parameter name.")?);
@@ -128,14 +345,71 @@ impl Parser {
 
         self.consume(&TokenType::RIGHTPAREN, "Expect ')' after parameters.")?;
 
-        self.consume(&TokenType::LEFTBRACE, "Expect '{' before function body.")?;
+        Ok(params.into())
+    }
 
+    fn lambda(&mut self) -> Result<Expr, LoxError> {
+        let keyword = self.previous();
+        self.consume(&TokenType::LEFTPAREN, "Expect '(' after 'fun'.")?;
+        let params = self.parameters()?;
+        self.consume(&TokenType::LEFTBRACE, "Expect '{' before function body.")?;
         let body = self.block()?;
 
-        Ok(Func { name, body, params })
+        Ok(Expr::Lambda(Lambda {
+            id: self.get_new_id(),
+            span: self.span_from(keyword.span),
+            params,
+            body: body.into(),
+        }))
     }
 
-    fn for_stmt(&mut self) -> Result<Stmt, ()> {
+    fn arrow_function(&mut self) -> Result<Expr, LoxError> {
+        let paren = self.consume(&TokenType::LEFTPAREN, "Expect '('.")?;
+        let params = self.parameters()?;
+        let arrow = self.consume(&TokenType::ARROW, "Expect '=>' after parameters.")?;
+        let value = self.expression()?;
+
+        let body = vec![Stmt::Return(ReturnStmt {
+            span: value.span(),
+            keyword: arrow,
+            value: Some(value),
+        })];
+        Ok(Expr::Lambda(Lambda {
+            id: self.get_new_id(),
+            span: self.span_from(paren.span),
+            params,
+            body: body.into(),
+        }))
+    }
+
+    /// Reports whether the tokens from the current `(` are the parameter list
+    /// of an arrow function rather than a grouping.
+    fn at_arrow_function(&self) -> bool {
+        let token_type = |i: usize| match self.tokens.get(i) {
+            Some(t) => t.token_type.clone(),
+            None => TokenType::EOF,
+        };
+
+        let mut i = self.current + 1;
+        if token_type(i) != TokenType::RIGHTPAREN {
+            loop {
+                if token_type(i) != TokenType::IDENTIFIER {
+                    return false;
+                }
+                i += 1;
+                match token_type(i) {
+                    TokenType::COMMA => i += 1,
+                    TokenType::RIGHTPAREN => break,
+                    _ => return false,
+                }
+            }
+        }
+
+        token_type(i + 1) == TokenType::ARROW
+    }
+
+    fn for_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
         self.consume(&TokenType::LEFTPAREN, "Expect '(' after 'for'.")?;
 
         let initializer = if self.matchh(vec![TokenType::SEMICOLON]) {
@@ -163,30 +437,31 @@ impl Parser {
         self.consume(&TokenType::RIGHTPAREN, "Expect ')' after loop clauses.")?;
 
         let mut body = self.statement()?;
-
-        if let Some(i) = increment {
-            body = Stmt::Block(Block {
-                stmts: vec![body, Stmt::ExprStmt(i)],
-            });
-        }
+        let span = self.span_from(keyword.span);
 
         if let Some(c) = condition {
-            body = Stmt::While(WhileStmt {
+            body = Stmt::While(Box::new(WhileStmt {
+                span,
                 condition: c,
                 body: Box::new(body),
-            });
+                increment,
+            }));
         } else {
-            body = Stmt::While(WhileStmt {
+            body = Stmt::While(Box::new(WhileStmt {
+                span,
                 condition: Expr::Literal(Literal {
                     id: self.get_new_id(),
+                    span: keyword.span,
                     value: Object::Bool(true),
                 }),
                 body: Box::new(body),
-            });
+                increment,
+            }));
         }
 
         if let Some(i) = initializer {
             body = Stmt::Block(Block {
+                span,
                 stmts: vec![i, body],
             });
         }
@@ -194,7 +469,8 @@ impl Parser {
         Ok(body)
     }
 
-    fn while_stmt(&mut self) -> Result<Stmt, ()> {
+    fn while_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(&TokenType::LEFTPAREN, "Expect '(' after 'if'.")?;
 
         let condition = self.expression()?;
@@ -203,13 +479,16 @@ impl Parser {
 
         let body = self.statement()?;
 
-        Ok(Stmt::While(WhileStmt {
+        Ok(Stmt::While(Box::new(WhileStmt {
+            span: self.span_from(start),
             condition,
             body: Box::new(body),
-        }))
+            increment: None,
+        })))
     }
 
-    fn if_stmt(&mut self) -> Result<Stmt, ()> {
+    fn if_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(&TokenType::LEFTPAREN, "Expect '(' after if statement.")?;
 
         let condition = self.expression()?;
@@ -223,25 +502,30 @@ impl Parser {
         }
 
         Ok(Stmt::If(IfStmt {
+            span: self.span_from(start),
             condition,
             then_block: Box::new(then_block),
             else_block,
         }))
     }
 
-    fn block(&mut self) -> Result<Vec<Stmt>, ()> {
+    fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
         let mut stmts = Vec::new();
 
+        self.blocks += 1;
         while !self.is_at_end() && !self.check(&TokenType::RIGHTBRACE) {
-            stmts.push(self.declaration()?);
+            if let Some(s) = self.recovering_declaration() {
+                stmts.push(s);
+            }
         }
+        self.blocks -= 1;
 
         self.consume(&TokenType::RIGHTBRACE, "Expect '}' after block.")?;
 
         Ok(stmts)
     }
 
-    fn expr_stmt(&mut self) -> Result<Stmt, ()> {
+    fn expr_stmt(&mut self) -> Result<Stmt, LoxError> {
         let expr = self.expression()?;
 
         self.consume(&TokenType::SEMICOLON, "Expect ';' after value.")?;
@@ -249,32 +533,82 @@ impl Parser {
         Ok(Stmt::ExprStmt(expr))
     }
 
-    fn expression(&mut self) -> Result<Expr, ()> {
+    fn expression(&mut self) -> Result<Expr, LoxError> {
         self.assignment()
     }
 
-    fn primary(&mut self) -> Result<Expr, ()> {
+    fn interpolation(&mut self) -> Result<Expr, LoxError> {
+        let start = self.previous().span;
+        let mut parts = Vec::new();
+        let mut piece = self.previous();
+
+        loop {
+            if piece.literal != Object::Str(String::new()) {
+                parts.push(Expr::Literal(Literal {
+                    id: self.get_new_id(),
+                    span: piece.span,
+                    value: piece.literal,
+                }));
+            }
+
+            if piece.token_type == TokenType::STRING {
+                break;
+            }
+
+            parts.push(self.expression()?);
+            if !self.matchh(vec![TokenType::INTERPOLATION]) {
+                self.consume(
+                    &TokenType::STRING,
+                    "Expect '}' after interpolated expression.",
+                )?;
+            }
+            piece = self.previous();
+        }
+
+        Ok(Expr::Interpolation(Interpolation {
+            id: self.get_new_id(),
+            span: self.span_from(start),
+            parts,
+        }))
+    }
+
+    fn primary(&mut self) -> Result<Expr, LoxError> {
+        if self.matchh(vec![TokenType::SUPER]) {
+            let keyword = self.previous();
+            self.consume(&TokenType::DOT, "Expect '.' after 'super'.")?;
+            let method = self.consume(&TokenType::IDENTIFIER, "Expect superclass method name.")?;
+            return Ok(Expr::Super(Super {
+                id: self.get_new_id(),
+                span: self.span_from(keyword.span),
+                keyword,
+                method,
+            }));
+        }
         if self.matchh(vec![TokenType::THIS]) {
             return Ok(Expr::This(This {
                 id: self.get_new_id(),
+                span: self.previous().span,
                 keyword: self.previous(),
             }));
         }
         if self.matchh(vec![TokenType::FALSE]) {
             return Ok(Expr::Literal(Literal {
                 id: self.get_new_id(),
+                span: self.previous().span,
                 value: Object::Bool(false),
             }));
         }
         if self.matchh(vec![TokenType::TRUE]) {
             return Ok(Expr::Literal(Literal {
                 id: self.get_new_id(),
+                span: self.previous().span,
                 value: Object::Bool(true),
             }));
         }
         if self.matchh(vec![TokenType::NIL]) {
             return Ok(Expr::Literal(Literal {
                 id: self.get_new_id(),
+                span: self.previous().span,
                 value: Object::None,
             }));
         }
@@ -282,15 +616,73 @@ impl Parser {
         if self.matchh(vec![TokenType::NUMBER, TokenType::STRING]) {
             return Ok(Expr::Literal(Literal {
                 id: self.get_new_id(),
+                span: self.previous().span,
                 value: self.previous().literal,
             }));
         }
 
+        if self.matchh(vec![TokenType::INTERPOLATION]) {
+            return self.interpolation();
+        }
+
+        if self.matchh(vec![TokenType::LEFTBRACKET]) {
+            let bracket = self.previous();
+            let mut elements = Vec::new();
+            while !self.check(&TokenType::RIGHTBRACKET) {
+                elements.push(self.expression()?);
+
+                if !self.matchh(vec![TokenType::COMMA]) {
+                    break;
+                }
+            }
+
+            self.consume(&TokenType::RIGHTBRACKET, "Expect ']' after list elements.")?;
+            return Ok(Expr::List(List {
+                id: self.get_new_id(),
+                span: self.span_from(bracket.span),
+                bracket,
+                elements,
+            }));
+        }
+
+        if self.matchh(vec![TokenType::LEFTBRACE]) {
+            let brace = self.previous();
+            let mut entries = Vec::new();
+            while !self.check(&TokenType::RIGHTBRACE) {
+                let key = self.expression()?;
+                self.consume(&TokenType::COLON, "Expect ':' after map key.")?;
+                let value = self.expression()?;
+                entries.push((key, value));
+
+                if !self.matchh(vec![TokenType::COMMA]) {
+                    break;
+                }
+            }
+
+            self.consume(&TokenType::RIGHTBRACE, "Expect '}' after map entries.")?;
+            return Ok(Expr::Map(Map {
+                id: self.get_new_id(),
+                span: self.span_from(brace.span),
+                brace,
+                entries,
+            }));
+        }
+
+        if self.matchh(vec![TokenType::FUN]) {
+            return self.lambda();
+        }
+
+        if self.check(&TokenType::LEFTPAREN) && self.at_arrow_function() {
+            return self.arrow_function();
+        }
+
         if self.matchh(vec![TokenType::LEFTPAREN]) {
+            let start = self.previous().span;
             let expr = self.expression()?;
             self.consume(&TokenType::RIGHTPAREN, "Expect ')' after expression")?;
             return Ok(Expr::Grouping(Grouping {
                 id: self.get_new_id(),
+                span: self.span_from(start),
                 expr: Box::new(expr),
             }));
         }
@@ -300,16 +692,15 @@ impl Parser {
 
             return Ok(Expr::Var(Variable {
                 id: self.get_new_id(),
+                span: name.span,
                 name,
             }));
         }
 
-        self.error(self.peek(), "Expect expression");
-
-        Err(())
+        Err(self.error(self.peek(), "Expect expression"))
     }
 
-    fn finish_call(&mut self, calle: Expr) -> Result<Expr, ()> {
+    fn finish_call(&mut self, calle: Expr) -> Result<Expr, LoxError> {
         let mut arguments = Vec::new();
         if !self.check(&TokenType::RIGHTPAREN) {
             arguments.push(self.expression()?);
@@ -322,13 +713,14 @@ impl Parser {
         let paren = self.consume(&TokenType::RIGHTPAREN, "Expect ')' after arguments.")?;
         Ok(Expr::Call(Call {
             id: self.get_new_id(),
+            span: calle.span().to(paren.span),
             calle: Box::new(calle),
             paren,
             arguments,
         }))
     }
 
-    fn call(&mut self) -> Result<Expr, ()> {
+    fn call(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.primary()?;
 
         loop {
@@ -338,33 +730,46 @@ impl Parser {
                 let name = self.consume(&TokenType::IDENTIFIER, "Expect identifier after '.'.")?;
                 expr = Expr::Get(Get {
                     id: self.get_new_id(),
+                    span: expr.span().to(name.span),
                     name,
                     expr: Box::new(expr),
                 });
+            } else if self.matchh(vec![TokenType::LEFTBRACKET]) {
+                let bracket = self.previous();
+                let index = self.expression()?;
+                self.consume(&TokenType::RIGHTBRACKET, "Expect ']' after index.")?;
+                expr = Expr::Index(Index {
+                    id: self.get_new_id(),
+                    span: self.span_from(expr.span()),
+                    expr: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
             } else {
                 break;
             }
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, ()> {
+    fn unary(&mut self) -> Result<Expr, LoxError> {
         if self.matchh(vec![TokenType::BANG, TokenType::MINUS]) {
             let operator = self.previous();
             let expr = self.unary()?;
 
             return Ok(Expr::Unary(Unary {
                 id: self.get_new_id(),
-                operator: operator,
+                span: operator.span.to(expr.span()),
+                operator,
                 right: Box::new(expr),
             }));
         }
 
-        return self.call();
+        self.call()
     }
 
-    fn factor(&mut self) -> Result<Expr, ()> {
+    fn factor(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.unary()?;
 
         while self.matchh(vec![TokenType::SLASH, TokenType::STAR]) {
@@ -372,8 +777,9 @@ impl Parser {
             let right = self.unary()?;
             expr = Expr::Binary(Binary {
                 id: self.get_new_id(),
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
-                operator: operator,
+                operator,
                 right: Box::new(right),
             });
         }
@@ -381,7 +787,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, ()> {
+    fn term(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.factor()?;
 
         while self.matchh(vec![TokenType::PLUS, TokenType::MINUS]) {
@@ -389,37 +795,40 @@ impl Parser {
             let right = self.factor()?;
             expr = Expr::Binary(Binary {
                 id: self.get_new_id(),
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
-                operator: operator,
+                operator,
                 right: Box::new(right),
             });
         }
         Ok(expr)
     }
 
-    fn comparision(&mut self) -> Result<Expr, ()> {
+    fn comparision(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.term()?;
 
         while self.matchh(vec![
             TokenType::GREATER,
             TokenType::GREATEREQUAL,
             TokenType::EQUALEQUAL,
+            TokenType::BANGEQUAL,
             TokenType::LESS,
             TokenType::LESSEQUAL,
         ]) {
             let operator = self.previous();
-            let right = self.factor()?;
+            let right = self.term()?;
             expr = Expr::Binary(Binary {
                 id: self.get_new_id(),
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
-                operator: operator,
+                operator,
                 right: Box::new(right),
             });
         }
         Ok(expr)
     }
 
-    fn and(&mut self) -> Result<Expr, ()> {
+    fn and(&mut self) -> Result<Expr, LoxError> {
         let mut left = self.comparision()?;
 
         while self.matchh(vec![TokenType::AND]) {
@@ -427,16 +836,17 @@ impl Parser {
             let right = self.comparision()?;
             left = Expr::Logical(Logical {
                 id: self.get_new_id(),
+                span: left.span().to(right.span()),
                 left: Box::new(left),
                 operator,
                 right: Box::new(right),
             });
         }
 
-        return Ok(left);
+        Ok(left)
     }
 
-    fn or(&mut self) -> Result<Expr, ()> {
+    fn or(&mut self) -> Result<Expr, LoxError> {
         let mut left = self.and()?;
 
         while self.matchh(vec![TokenType::OR]) {
@@ -444,50 +854,63 @@ impl Parser {
             let right = self.and()?;
             left = Expr::Logical(Logical {
                 id: self.get_new_id(),
+                span: left.span().to(right.span()),
                 left: Box::new(left),
                 operator,
                 right: Box::new(right),
             });
         }
 
-        return Ok(left);
+        Ok(left)
     }
 
-    fn assignment(&mut self) -> Result<Expr, ()> {
+    fn assignment(&mut self) -> Result<Expr, LoxError> {
         let expr = self.or()?;
 
         if self.matchh(vec![TokenType::EQUAL]) {
             let equals = self.previous();
             let value = self.assignment()?;
+            let span = expr.span().to(value.span());
 
             if let Expr::Var(v) = expr {
                 return Ok(Expr::Assign(Assign {
                     id: self.get_new_id(),
+                    span,
                     name: v.name,
                     value: Box::new(value),
                 }));
             } else if let Expr::Get(g) = expr {
                 return Ok(Expr::Set(Set {
                     id: self.get_new_id(),
+                    span,
                     name: g.name.clone(),
                     value: Box::new(value),
                     expr: g.expr,
                 }));
+            } else if let Expr::Index(i) = expr {
+                return Ok(Expr::SetIndex(SetIndex {
+                    id: self.get_new_id(),
+                    span,
+                    expr: i.expr,
+                    bracket: i.bracket,
+                    index: i.index,
+                    value: Box::new(value),
+                }));
             }
 
-            self.error(equals, "Invalid assignment target.");
-            return Err(());
+            return Err(self.error(equals, "Invalid assignment target."));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
-    fn error(&self, token: Token, message: &str) {
-        if token.token_type == TokenType::EOF {
-            eprintln!("[Line: {}] at {} '{}'", token.line, "end", message);
-        } else {
-            eprintln!("[Line: {}] at {:?} '{}'", token.line, token.lexeme, message);
-        };
+    /// The span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
+
+    fn error(&self, token: Token, message: &str) -> LoxError {
+        LoxError::parse(&token, message)
     }
 
     fn is_at_end(&self) -> bool {
@@ -514,6 +937,13 @@ impl Parser {
         self.peek().token_type == *token_type
     }
 
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(t) => t.token_type == *token_type,
+            None => false,
+        }
+    }
+
     fn matchh(&mut self, types: Vec<TokenType>) -> bool {
         for t in &types {
             if self.check(t) {
@@ -522,17 +952,15 @@ impl Parser {
             }
         }
 
-        return false;
+        false
     }
 
-    fn consume(&mut self, token_type: &TokenType, message: &str) -> Result<Token, ()> {
+    fn consume(&mut self, token_type: &TokenType, message: &str) -> Result<Token, LoxError> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
 
-        self.error(self.peek(), message);
-
-        Err(())
+        Err(self.error(self.peek(), message))
     }
 
     fn get_new_id(&mut self) -> usize {
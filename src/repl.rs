@@ -0,0 +1,311 @@
+use rlox::completion;
+use rlox::error::LoxError;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::{Scanner, Token};
+use rlox::statements::Stmt;
+use rlox::types::Object;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::io::{self, BufRead, IsTerminal};
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::str::Chars;
+
+const HISTORY_FILE: &str = ".rlox_history";
+
+struct Repl {
+    interpreter: Interpreter,
+    // Expression ids must stay unique across lines, because the interpreter
+    // keeps the resolved depths of every line it has seen.
+    next_id: usize,
+}
+
+impl Repl {
+    fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+            next_id: 0,
+        }
+    }
+
+    fn run(&mut self, source: &str) {
+        if let Err(errors) = self.try_run(source) {
+            let color = io::stderr().is_terminal();
+            for e in errors {
+                eprintln!("{}\n", e.render(source, "<repl>", color));
+            }
+        }
+    }
+
+    /// Parses the input as statements or, if it isn't any, as a single
+    /// expression, so that `1 + 2` or `{"a": 1}` can be typed on their own.
+    fn parse(&mut self, tokens: Vec<Token>) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let mut parser = Parser::with_next_id(tokens.clone(), self.next_id);
+        let (stmts, errors) = parser.parse();
+        self.next_id = parser.next_id();
+        if errors.is_empty() {
+            return Ok(stmts);
+        }
+
+        let mut parser = Parser::with_next_id(tokens, self.next_id);
+        let expr = parser.parse_expression().map_err(|_| errors)?;
+        self.next_id = parser.next_id();
+
+        Ok(vec![Stmt::ExprStmt(expr)])
+    }
+
+    fn try_run(&mut self, source: &str) -> Result<(), Vec<LoxError>> {
+        let tokens = Scanner::new(source.chars().peekable()).scan_tokens()?;
+        let stmts = self.parse(tokens)?;
+
+        let mut resolver = Resolver::new(Vec::new(), &mut self.interpreter);
+        resolver.resolve_stmts(&stmts)?;
+
+        if let [Stmt::ExprStmt(expr)] = stmts.as_slice() {
+            match self.interpreter.interpret_expr(expr) {
+                Ok(Object::None) => Ok(()),
+                Ok(value) => {
+                    println!("{}", value);
+                    Ok(())
+                }
+                Err(e) => Err(vec![e.into()]),
+            }
+        } else {
+            self.interpreter.interpret(stmts).map_err(|e| vec![e])
+        }
+    }
+}
+
+/// Where the prompt gets its input from.
+trait LineReader {
+    /// Returns `None` once there is no more input.
+    fn read_line(&mut self, prompt: &str, repl: &Repl) -> Option<String>;
+
+    fn add_history(&mut self, _entry: &str) {}
+}
+
+/// Reads plain lines from stdin, for when it isn't a terminal. No prompt is
+/// shown, so that piped output holds only what the program prints.
+struct PlainReader;
+
+impl LineReader for PlainReader {
+    fn read_line(&mut self, _: &str, _: &Repl) -> Option<String> {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(line),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Completes keywords and the names of globals defined in the session.
+struct LoxHelper {
+    globals: Vec<String>,
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(completion::complete(line, pos, &self.globals))
+    }
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
+
+/// Reads lines with editing, history and tab completion.
+struct EditorReader {
+    editor: Editor<LoxHelper, DefaultHistory>,
+    history: Option<PathBuf>,
+}
+
+impl EditorReader {
+    fn new() -> rustyline::Result<Self> {
+        let mut editor = Editor::new()?;
+        editor.set_helper(Some(LoxHelper {
+            globals: Vec::new(),
+        }));
+
+        let history = std::env::home_dir().map(|home| home.join(HISTORY_FILE));
+        if let Some(path) = &history {
+            // There is no history yet the first time the prompt is used.
+            let _ = editor.load_history(path);
+        }
+
+        Ok(Self { editor, history })
+    }
+}
+
+impl LineReader for EditorReader {
+    fn read_line(&mut self, prompt: &str, repl: &Repl) -> Option<String> {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.globals = repl.interpreter.global_names();
+        }
+
+        loop {
+            match self.editor.readline(prompt) {
+                Ok(line) => return Some(line + "\n"),
+                // Ctrl-C abandons the current line rather than the session.
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => {
+                    // Leave the line the prompt is on.
+                    println!();
+                    return None;
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn add_history(&mut self, entry: &str) {
+        let _ = self.editor.add_history_entry(entry.trim_end());
+        if let Some(path) = &self.history
+            && let Err(e) = self.editor.save_history(path)
+        {
+            eprintln!("Couldn't save history: {}", e);
+        }
+    }
+}
+
+pub fn run_prompt() {
+    if io::stdin().is_terminal() {
+        match EditorReader::new() {
+            Ok(reader) => return run_with(reader),
+            Err(e) => eprintln!("Couldn't start line editor: {}", e),
+        }
+    }
+
+    run_with(PlainReader);
+}
+
+fn run_with(mut reader: impl LineReader) {
+    let mut repl = Repl::new();
+    let mut buffer = String::new();
+
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { "... " };
+        match reader.read_line(prompt, &repl) {
+            Some(line) => buffer.push_str(&line),
+            None => break,
+        }
+
+        if is_incomplete(&buffer) {
+            continue;
+        }
+
+        if !buffer.trim().is_empty() {
+            reader.add_history(&buffer);
+            repl.run(&buffer);
+        }
+        buffer.clear();
+    }
+}
+
+/// Reports whether `source` has more opening than closing brackets, ignoring
+/// those inside string literals and comments, or ends inside a string or
+/// block comment.
+fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0i64;
+    let mut chars = source.chars().peekable();
+    let mut previous = None;
+    let mut starts_token = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            // An unterminated string keeps the input open, since strings may
+            // span lines. As in the scanner, `r"` only starts a raw string
+            // when the `r` isn't the end of a longer identifier such as `or`.
+            '"' if !skip_string(&mut chars, previous == Some('r') && starts_token) => {
+                return true;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '/' if chars.next_if_eq(&'*').is_some() && !skip_block_comment(&mut chars) => {
+                return true;
+            }
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            _ => (),
+        }
+        starts_token = !previous.is_some_and(Scanner::is_alpha_numeric);
+        previous = Some(c);
+    }
+
+    depth > 0
+}
+
+/// Skips the rest of a (possibly nested) block comment whose `/*` has been
+/// read, returning whether it was closed.
+fn skip_block_comment(chars: &mut Peekable<Chars>) -> bool {
+    let mut depth = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.next_if_eq(&'*').is_some() => depth += 1,
+            '*' if chars.next_if_eq(&'/').is_some() => {
+                depth -= 1;
+                if depth == 0 {
+                    return true;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    false
+}
+
+/// Skips the rest of a string literal whose opening quote has been read,
+/// returning whether it was closed.
+fn skip_string(chars: &mut Peekable<Chars>, raw: bool) -> bool {
+    let mut lookahead = chars.clone();
+    let triple = lookahead.next() == Some('"') && lookahead.next() == Some('"');
+    if triple {
+        chars.nth(1);
+    } else if chars.peek() == Some(&'"') {
+        chars.next();
+        return true;
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if !raw => {
+                chars.next();
+            }
+            '"' if !triple => return true,
+            '"' if chars.next_if_eq(&'"').is_some() && chars.next_if_eq(&'"').is_some() => {
+                return true;
+            }
+            _ => (),
+        }
+    }
+
+    false
+}
@@ -1,14 +1,17 @@
+use crate::error::LoxError;
 use crate::expressions::{
-    Assign, Binary, Call, Expr, Get, Grouping, Literal, Logical, Set, This, Unary, Variable,
-    VisitableE, VisitorE,
+    Assign, Binary, Call, Expr, Get, Grouping, Index, Interpolation, Lambda, List, Literal,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable, VisitableE, VisitorE,
 };
-use crate::interpreter::Interpreter;
-use crate::scanner::Token;
+use crate::interpreter::{Interpreter, Slot};
+use crate::scanner::{Span, Token};
 use crate::statements::{
-    Block, Class, Func, IfStmt, ReturnStmt, Stmt, Var, VisitableS, VisitorS, WhileStmt,
+    Block, BreakStmt, Class, ContinueStmt, Func, IfStmt, ReturnStmt, Stmt, ThrowStmt, TryStmt, Var,
+    VisitableS, VisitorS, WhileStmt,
 };
 use std::collections::HashMap;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, PartialEq)]
 enum FunctionType {
     FUNCTION,
@@ -17,26 +20,41 @@ enum FunctionType {
     NONE,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone)]
 enum ClassType {
     CLASS,
+    SUBCLASS,
     NONE,
 }
 
+/// A name declared in a local scope.
+pub struct Binding {
+    defined: bool,
+    // Where the variable is stored in its scope's environment.
+    slot: usize,
+    // Where the name was declared, for pointing at it in diagnostics.
+    span: Span,
+}
+
 pub struct Resolver<'a> {
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<HashMap<String, Binding>>,
     interpreter: &'a mut Interpreter,
     current_function: FunctionType,
     current_class: ClassType,
+    loop_depth: usize,
+    errors: Vec<LoxError>,
 }
 
 impl<'a> Resolver<'a> {
-    pub fn new(scopes: Vec<HashMap<String, bool>>, interpreter: &'a mut Interpreter) -> Self {
+    pub fn new(scopes: Vec<HashMap<String, Binding>>, interpreter: &'a mut Interpreter) -> Self {
         Self {
             scopes,
             interpreter,
             current_function: FunctionType::NONE,
             current_class: ClassType::NONE,
+            loop_depth: 0,
+            errors: Vec::new(),
         }
     }
 
@@ -48,254 +66,386 @@ impl<'a> Resolver<'a> {
         self.scopes.pop();
     }
 
-    fn declare(&mut self, name: Token) -> Option<()> {
+    fn error(&mut self, error: LoxError) {
+        self.errors.push(error);
+    }
+
+    fn declare(&mut self, name: Token) {
         if self.scopes.is_empty() {
-            return Some(());
+            return;
         }
 
-        if let Some(_) = self.scopes[self.scopes.len() - 1].get(&name.lexeme.clone().unwrap()) {
-            eprintln!(
-                "Already a variable with this name in this scope. [Line: {}]",
-                name.line
-            );
-            return None;
+        if let Some(b) = self.scopes[self.scopes.len() - 1].get(&name.lexeme.clone()) {
+            let error =
+                LoxError::resolve(&name, "Already a variable with this name in this scope.")
+                    .with_label(b.span, "variable declared here");
+            self.error(error);
+            return;
         }
 
-        self.scopes
-            .last_mut()
-            .unwrap()
-            .insert(name.lexeme.unwrap(), false);
-
-        Some(())
+        let scope = self.scopes.last_mut().unwrap();
+        let slot = scope.len();
+        scope.insert(
+            name.lexeme,
+            Binding {
+                defined: false,
+                slot,
+                span: name.span,
+            },
+        );
     }
 
-    fn define(&mut self, name: Token) -> Option<()> {
+    fn define(&mut self, name: Token) {
         if self.scopes.is_empty() {
-            return Some(());
+            return;
         }
 
-        self.scopes
-            .last_mut()
-            .unwrap()
-            .insert(name.lexeme.unwrap(), true);
+        if let Some(b) = self.scopes.last_mut().unwrap().get_mut(&name.lexeme) {
+            b.defined = true;
+        }
+    }
 
-        Some(())
+    /// Opens a scope holding just `name`, like the ones for `this` and
+    /// `super`.
+    fn begin_scope_with(&mut self, name: &str, span: Span) {
+        self.begin_scope();
+        self.scopes.last_mut().unwrap().insert(
+            name.to_string(),
+            Binding {
+                defined: true,
+                slot: 0,
+                span,
+            },
+        );
     }
 
-    pub fn resolve_stmts(&mut self, stmts: &mut Vec<Stmt>) -> Option<()> {
-        for stmt in stmts.iter_mut() {
-            self.resolve_stmt(stmt)?;
+    /// Resolves a whole program, reporting every error found rather than
+    /// stopping at the first.
+    pub fn resolve_stmts(&mut self, stmts: &[Stmt]) -> Result<(), Vec<LoxError>> {
+        self.resolve_block(stmts);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
+    }
 
-        Some(())
+    fn resolve_block(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts.iter() {
+            self.resolve_stmt(stmt);
+        }
     }
 
-    fn resolve_stmt(&mut self, stmt: &mut Stmt) -> Option<()> {
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
         stmt.accept(self)
     }
 
-    fn resolve_expr(&mut self, expr: &mut Expr) -> Option<()> {
+    fn resolve_expr(&mut self, expr: &Expr) {
         expr.accept(self)
     }
 
-    fn resolve_fun(&mut self, func: &mut Func, t: FunctionType) -> Option<()> {
+    fn resolve_fun(&mut self, params: &[Token], body: &[Stmt], t: FunctionType) {
         let enclosing = self.current_function.clone();
+        let enclosing_loop_depth = self.loop_depth;
         self.current_function = t;
+        self.loop_depth = 0;
         self.begin_scope();
-        for param in &func.params {
-            self.declare(param.clone())?;
-            self.define(param.clone())?;
+        for param in params {
+            self.declare(param.clone());
+            self.define(param.clone());
         }
 
-        self.resolve_stmts(&mut func.body)?;
+        self.resolve_block(body);
         self.end_scope();
 
         self.current_function = enclosing;
-
-        Some(())
+        self.loop_depth = enclosing_loop_depth;
     }
 
-    fn resolve_local(&mut self, expr: Expr, name: Token) {
+    /// Records where the variable `name`, used by the expression with id
+    /// `id`, is stored. Names not found in any scope are globals.
+    fn resolve_local(&mut self, id: usize, name: &Token) {
         for i in (0..self.scopes.len()).rev() {
-            if self.scopes[i].contains_key(&name.lexeme.clone().unwrap()) {
-                self.interpreter.resolve(expr, self.scopes.len() - 1 - i);
+            if let Some(b) = self.scopes[i].get(&name.lexeme) {
+                let slot = Slot {
+                    depth: self.scopes.len() - 1 - i,
+                    index: b.slot,
+                };
+                self.interpreter.resolve(id, slot);
                 return;
             }
         }
     }
 }
 
-impl<'a> VisitorE<Option<()>> for Resolver<'a> {
-    fn visit_this(&mut self, expr: &This) -> Option<()> {
+impl<'a> VisitorE<()> for Resolver<'a> {
+    fn visit_super(&mut self, expr: &Super) {
+        match self.current_class {
+            ClassType::NONE => {
+                self.error(LoxError::resolve(
+                    &expr.keyword,
+                    "Can't use 'super' outside of a class.",
+                ));
+                return;
+            }
+            ClassType::CLASS => {
+                self.error(LoxError::resolve(
+                    &expr.keyword,
+                    "Can't use 'super' in a class with no superclass.",
+                ));
+                return;
+            }
+            ClassType::SUBCLASS => (),
+        }
+        self.resolve_local(expr.id, &expr.keyword);
+    }
+
+    fn visit_this(&mut self, expr: &This) {
         if let ClassType::NONE = self.current_class {
-            eprintln!(
-                "Can't use `this` keyword from top-level code. [Line: {}]",
-                expr.keyword.line
-            );
-            return None;
+            self.error(LoxError::resolve(
+                &expr.keyword,
+                "Can't use `this` keyword from top-level code.",
+            ));
+            return;
+        }
+        self.resolve_local(expr.id, &expr.keyword);
+    }
+    fn visit_set(&mut self, expr: &Set) {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.expr);
+    }
+
+    fn visit_get(&mut self, expr: &Get) {
+        self.resolve_expr(&expr.expr);
+    }
+
+    fn visit_list(&mut self, expr: &List) {
+        for element in expr.elements.iter() {
+            self.resolve_expr(element);
         }
-        self.resolve_local(Expr::This(expr.clone()), expr.keyword.clone());
-        Some(())
     }
-    fn visit_set(&mut self, expr: &Set) -> Option<()> {
-        self.resolve_expr(&mut expr.value.clone());
-        self.resolve_expr(&mut expr.expr.clone());
-        Some(())
+
+    fn visit_lambda(&mut self, expr: &Lambda) {
+        self.resolve_fun(&expr.params, &expr.body, FunctionType::FUNCTION);
+    }
+
+    fn visit_interpolation(&mut self, expr: &Interpolation) {
+        for part in expr.parts.iter() {
+            self.resolve_expr(part);
+        }
     }
 
-    fn visit_get(&mut self, expr: &Get) -> Option<()> {
-        self.resolve_expr(&mut expr.expr.clone());
-        Some(())
+    fn visit_map(&mut self, expr: &Map) {
+        for (key, value) in expr.entries.iter() {
+            self.resolve_expr(key);
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_index(&mut self, expr: &Index) {
+        self.resolve_expr(&expr.expr);
+        self.resolve_expr(&expr.index)
     }
 
-    fn visit_binary(&mut self, expr: &Binary) -> Option<()> {
-        self.resolve_expr(&mut expr.left.clone())?;
-        self.resolve_expr(&mut expr.right.clone())
+    fn visit_set_index(&mut self, expr: &SetIndex) {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.expr);
+        self.resolve_expr(&expr.index)
     }
 
-    fn visit_unary(&mut self, expr: &Unary) -> Option<()> {
-        self.resolve_expr(&mut expr.right.clone())
+    fn visit_binary(&mut self, expr: &Binary) {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right)
     }
 
-    fn visit_grouping(&mut self, expr: &Grouping) -> Option<()> {
-        self.resolve_expr(&mut expr.expr.clone())
+    fn visit_unary(&mut self, expr: &Unary) {
+        self.resolve_expr(&expr.right)
     }
 
-    fn visit_literal(&mut self, _: &Literal) -> Option<()> {
-        Some(())
+    fn visit_grouping(&mut self, expr: &Grouping) {
+        self.resolve_expr(&expr.expr)
     }
 
-    fn visit_variable(&mut self, expr: &Variable) -> Option<()> {
+    fn visit_literal(&mut self, _: &Literal) {}
+
+    fn visit_variable(&mut self, expr: &Variable) {
         if !self.scopes.is_empty()
-            && self.scopes[self.scopes.len() - 1].get(&expr.name.lexeme.clone().unwrap()) != None
-            && *self.scopes[self.scopes.len() - 1]
-                .get(&expr.name.lexeme.clone().unwrap())
-                .unwrap()
-                == false
+            && matches!(
+                self.scopes[self.scopes.len() - 1].get(&expr.name.lexeme.clone()),
+                Some(b) if !b.defined
+            )
         {
-            eprintln!(
-                "Can't read local variable in its own initializer. [Line: {}]",
-                expr.name.line
-            );
-            return None;
+            self.error(LoxError::resolve(
+                &expr.name,
+                "Can't read local variable in its own initializer.",
+            ));
+            return;
         }
-        self.resolve_local(Expr::Var(expr.clone()), expr.name.clone());
-        Some(())
+        self.resolve_local(expr.id, &expr.name);
     }
 
-    fn visit_assign(&mut self, expr: &Assign) -> Option<()> {
-        let mut a = expr.clone();
-        self.resolve_expr(&mut a.value)?;
-        self.resolve_local(Expr::Assign(a.clone()), a.name.clone());
-
-        Some(())
+    fn visit_assign(&mut self, expr: &Assign) {
+        self.resolve_expr(&expr.value);
+        self.resolve_local(expr.id, &expr.name);
     }
 
-    fn visit_logical(&mut self, expr: &Logical) -> Option<()> {
-        self.resolve_expr(&mut expr.left.clone())?;
-        self.resolve_expr(&mut expr.right.clone())
+    fn visit_logical(&mut self, expr: &Logical) {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right)
     }
 
-    fn visit_call(&mut self, expr: &Call) -> Option<()> {
-        self.resolve_expr(&mut expr.calle.clone())?;
+    fn visit_call(&mut self, expr: &Call) {
+        self.resolve_expr(&expr.calle);
 
-        for arg in expr.arguments.clone().iter_mut() {
-            self.resolve_expr(arg)?;
+        for arg in expr.arguments.iter() {
+            self.resolve_expr(arg);
         }
-
-        Some(())
     }
 }
 
-impl<'a> VisitorS<Option<()>> for Resolver<'a> {
-    fn visit_class_stmt(&mut self, stmt: &mut Class) -> Option<()> {
+impl<'a> VisitorS<()> for Resolver<'a> {
+    fn visit_class_stmt(&mut self, stmt: &Class) {
         let enclosing_class = self.current_class.clone();
         self.current_class = ClassType::CLASS;
         self.declare(stmt.name.clone());
         self.define(stmt.name.clone());
 
-        self.begin_scope();
-        self.scopes
-            .last_mut()
-            .unwrap()
-            .insert("this".to_string(), true);
-        for method in stmt.methods.iter_mut() {
+        if let Some(s) = &stmt.superclass {
+            if s.name.lexeme == stmt.name.lexeme {
+                self.error(LoxError::resolve(
+                    &s.name,
+                    "A class can't inherit from itself.",
+                ));
+            } else {
+                self.visit_variable(s);
+            }
+
+            self.current_class = ClassType::SUBCLASS;
+            self.begin_scope_with("super", s.name.span);
+        }
+
+        self.begin_scope_with("this", stmt.name.span);
+        for method in stmt.methods.iter() {
             let mut declaration = FunctionType::METHOD;
-            if method.name.lexeme.clone().unwrap() == "init".to_string() {
+            if method.name.lexeme == "init" {
                 declaration = FunctionType::INITIALIZER;
             }
-            self.resolve_fun(method, declaration);
+            self.resolve_fun(&method.params, &method.body, declaration);
         }
         self.end_scope();
-        self.current_class = enclosing_class;
 
-        Some(())
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
     }
 
-    fn visit_block_stmt(&mut self, stmt: &mut Block) -> Option<()> {
+    fn visit_block_stmt(&mut self, stmt: &Block) {
         self.begin_scope();
-        self.resolve_stmts(&mut stmt.stmts)?;
+        self.resolve_block(&stmt.stmts);
         self.end_scope();
-
-        Some(())
     }
 
-    fn visit_expr_stmt(&mut self, stmt: &mut Expr) -> Option<()> {
+    fn visit_expr_stmt(&mut self, stmt: &Expr) {
         self.resolve_expr(stmt)
     }
 
-    fn visit_var_stmt(&mut self, stmt: &mut Var) -> Option<()> {
-        self.declare(stmt.token.clone())?;
-        if let Some(init) = &mut stmt.initializer {
-            self.resolve_expr(init)?;
+    fn visit_var_stmt(&mut self, stmt: &Var) {
+        self.declare(stmt.token.clone());
+        if let Some(init) = &stmt.initializer {
+            self.resolve_expr(init);
         }
         self.define(stmt.token.clone())
     }
 
-    fn visit_if_stmt(&mut self, stmt: &mut IfStmt) -> Option<()> {
-        self.resolve_expr(&mut stmt.condition)?;
-        self.resolve_stmt(&mut stmt.then_block)?;
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        self.resolve_expr(&stmt.condition);
+        self.resolve_stmt(&stmt.then_block);
+
+        if let Some(e) = &stmt.else_block {
+            self.resolve_stmt(e);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        self.resolve_expr(&stmt.condition);
 
-        if let Some(e) = &mut stmt.else_block {
-            self.resolve_stmt(e)?;
+        self.loop_depth += 1;
+        self.resolve_stmt(&stmt.body);
+        if let Some(i) = &stmt.increment {
+            self.resolve_expr(i);
         }
+        self.loop_depth -= 1;
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) {
+        if self.loop_depth == 0 {
+            self.error(LoxError::resolve(
+                &stmt.keyword,
+                "Can't use 'break' outside of a loop.",
+            ));
+        }
+    }
 
-        Some(())
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) {
+        if self.loop_depth == 0 {
+            self.error(LoxError::resolve(
+                &stmt.keyword,
+                "Can't use 'continue' outside of a loop.",
+            ));
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &ThrowStmt) {
+        self.resolve_expr(&stmt.value);
     }
 
-    fn visit_while_stmt(&mut self, stmt: &mut WhileStmt) -> Option<()> {
-        self.resolve_expr(&mut stmt.condition)?;
-        self.resolve_stmt(&mut stmt.body)
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) {
+        self.begin_scope();
+        self.resolve_block(&stmt.body);
+        self.end_scope();
+
+        if let Some(catch) = &stmt.catch {
+            self.begin_scope();
+            self.declare(catch.name.clone());
+            self.define(catch.name.clone());
+            self.resolve_block(&catch.body);
+            self.end_scope();
+        }
+
+        if let Some(finally) = &stmt.finally {
+            self.begin_scope();
+            self.resolve_block(finally);
+            self.end_scope();
+        }
     }
 
-    fn visit_func_stmt(&mut self, stmt: &mut Func) -> Option<()> {
-        self.declare(stmt.name.clone())?;
-        self.define(stmt.name.clone())?;
+    fn visit_func_stmt(&mut self, stmt: &Func) {
+        self.declare(stmt.name.clone());
+        self.define(stmt.name.clone());
 
-        self.resolve_fun(stmt, FunctionType::FUNCTION)
+        self.resolve_fun(&stmt.params, &stmt.body, FunctionType::FUNCTION)
     }
 
-    fn visit_return_stmt(&mut self, stmt: &mut ReturnStmt) -> Option<()> {
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
         if let FunctionType::NONE = self.current_function {
-            eprintln!(
-                "Can't return from top-level code. [Line: {}]",
-                stmt.keyword.line
-            );
-            return None;
+            self.error(LoxError::resolve(
+                &stmt.keyword,
+                "Can't return from top-level code.",
+            ));
         }
 
-        if let Some(e) = &mut stmt.value {
+        if let Some(e) = &stmt.value {
             if self.current_function == FunctionType::INITIALIZER {
-                eprintln!(
-                    "Can't return a value from an initializer. [Line: {}]",
-                    stmt.keyword.line
-                );
-                return None;
+                self.error(LoxError::resolve(
+                    &stmt.keyword,
+                    "Can't return a value from an initializer.",
+                ));
             }
 
-            self.resolve_expr(e)?;
+            self.resolve_expr(e);
         }
-
-        Some(())
     }
 }
@@ -1,3 +1,4 @@
+use crate::error::{Location, LoxError};
 use crate::types::Object;
 use TokenType::*;
 use std::iter::{Iterator, Peekable};
@@ -7,9 +8,13 @@ use std::{collections::HashMap, sync::LazyLock};
 pub static KEYWORDS: LazyLock<HashMap<&'static str, TokenType>> = LazyLock::new(|| {
     HashMap::from([
         ("and", AND),
+        ("break", BREAK),
+        ("catch", CATCH),
         ("class", CLASS),
+        ("continue", CONTINUE),
         ("else", ELSE),
         ("false", FALSE),
+        ("finally", FINALLY),
         ("fun", FUN),
         ("for", FOR),
         ("if", IF),
@@ -18,7 +23,9 @@ pub static KEYWORDS: LazyLock<HashMap<&'static str, TokenType>> = LazyLock::new(
         ("return", RETURN),
         ("super", SUPER),
         ("this", THIS),
+        ("throw", THROW),
         ("true", TRUE),
+        ("try", TRY),
         ("var", VAR),
         ("while", WHILE),
     ])
@@ -31,7 +38,10 @@ pub enum TokenType {
     RIGHTPAREN,
     LEFTBRACE,
     RIGHTBRACE,
+    LEFTBRACKET,
+    RIGHTBRACKET,
     COMMA,
+    COLON,
     DOT,
     MINUS,
     PLUS,
@@ -48,17 +58,25 @@ pub enum TokenType {
     GREATEREQUAL,
     LESS,
     LESSEQUAL,
+    ARROW,
 
     // Literals
     IDENTIFIER,
     STRING,
+    // The part of a string before a `${`, which is followed by the tokens of
+    // the embedded expression and then the rest of the string.
+    INTERPOLATION,
     NUMBER,
 
     // Keywords
     AND,
+    BREAK,
+    CATCH,
     CLASS,
+    CONTINUE,
     ELSE,
     FALSE,
+    FINALLY,
     FUN,
     FOR,
     IF,
@@ -68,37 +86,95 @@ pub enum TokenType {
     RETURN,
     SUPER,
     THIS,
+    THROW,
     TRUE,
+    TRY,
     VAR,
     WHILE,
 
     EOF,
 }
 
+/// A range of byte offsets into the source.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub line: u64,
-    pub lexeme: Option<String>,
+    pub column: u64,
+    pub lexeme: String,
     pub literal: Object,
+    pub span: Span,
+    /// The text of any `///` comments just before the token.
+    pub doc: Option<String>,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, line: u64, lexeme: Option<String>, literal: Object) -> Token {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        literal: Object,
+        line: u64,
+        column: u64,
+        span: Span,
+    ) -> Token {
         Token {
             token_type,
             line,
+            column,
             lexeme,
             literal,
+            span,
+            doc: None,
         }
     }
 }
 
+/// A string whose `${...}` the scanner is inside of.
+struct Interpolation {
+    // Unclosed `{` seen since the `${`, so the matching `}` can be found.
+    depth: usize,
+    triple: bool,
+    start: usize,
+    line: u64,
+    column: u64,
+}
+
 pub struct Scanner<'a> {
     source: Peekable<Chars<'a>>,
     tokens: Vec<Token>,
     line: u64,
-    had_error: bool,
+    column: u64,
+    // Byte offset of the next character.
+    current: usize,
+    // Position and text of the token being scanned.
+    start: usize,
+    start_line: u64,
+    start_column: u64,
+    lexeme: String,
+    interpolations: Vec<Interpolation>,
+    // Doc comment lines waiting to be attached to the next token.
+    doc: Option<String>,
+    errors: Vec<LoxError>,
 }
 
 impl<'a> Scanner<'a> {
@@ -107,278 +183,442 @@ impl<'a> Scanner<'a> {
             source,
             tokens: Vec::<Token>::new(),
             line: 1,
-            had_error: false,
+            column: 1,
+            current: 0,
+            start: 0,
+            start_line: 1,
+            start_column: 1,
+            lexeme: String::new(),
+            interpolations: Vec::new(),
+            doc: None,
+            errors: Vec::new(),
         }
     }
 
-    fn is_alpha_numeric(c: char) -> bool {
-        match c {
-            '_' | 'a'..='z' | 'A'..='Z' | '0'..='9' => true,
-            _ => false,
-        }
+    pub fn is_alpha_numeric(c: char) -> bool {
+        matches!(c, '_' | 'a'..='z' | 'A'..='Z' | '0'..='9')
     }
 
-    pub fn error(&mut self, line: u64, message: &str) {
-        self.had_error = true;
-        eprintln!("[line: {}] Error: {}", line, message);
+    pub fn error(&mut self, message: &str) {
+        let location = Location {
+            line: self.start_line,
+            column: self.start_column,
+            span: Span::new(self.start, self.current),
+            lexeme: None,
+        };
+        self.errors.push(LoxError::scan(location, message));
     }
 
-    pub fn scan_tokens(&mut self) -> Option<Vec<Token>> {
+    /// Reports an error for part of the current token, starting at `start`.
+    fn error_at(&mut self, start: usize, line: u64, column: u64, message: &str) {
+        let location = Location {
+            line,
+            column,
+            span: Span::new(start, self.current),
+            lexeme: None,
+        };
+        self.errors.push(LoxError::scan(location, message));
+    }
+
+    /// Scans the whole source, returning every error found if there were any.
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<LoxError>> {
         while !self.is_at_end() {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
+            self.lexeme.clear();
             self.scan_token();
         }
 
-        if self.had_error {
-            return None;
+        while let Some(i) = self.interpolations.pop() {
+            self.error_at(
+                i.start,
+                i.line,
+                i.column,
+                "Unterminated string interpolation.",
+            );
         }
 
-        self.tokens
-            .push(Token::new(TokenType::EOF, self.line, None, Object::None));
-        Some(self.tokens.clone())
+        if !self.errors.is_empty() {
+            return Err(self.errors.clone());
+        }
+
+        self.tokens.push(Token::new(
+            TokenType::EOF,
+            String::new(),
+            Object::None,
+            self.line,
+            self.column,
+            Span::new(self.current, self.current),
+        ));
+        Ok(self.tokens.clone())
     }
 
     fn scan_token(&mut self) {
-        let c = self.source.next().unwrap();
+        let c = self.advance();
         match c {
-            '{' => self.tokens.push(Token::new(
-                TokenType::LEFTBRACE,
-                self.line,
-                None,
-                Object::None,
-            )),
-            '}' => self.tokens.push(Token::new(
-                TokenType::RIGHTBRACE,
-                self.line,
-                None,
-                Object::None,
-            )),
-            '(' => self.tokens.push(Token::new(
-                TokenType::LEFTPAREN,
-                self.line,
-                None,
-                Object::None,
-            )),
-            ')' => self.tokens.push(Token::new(
-                TokenType::RIGHTPAREN,
-                self.line,
-                None,
-                Object::None,
-            )),
-            ',' => self
-                .tokens
-                .push(Token::new(TokenType::COMMA, self.line, None, Object::None)),
-            '.' => self
-                .tokens
-                .push(Token::new(TokenType::DOT, self.line, None, Object::None)),
-            ';' => self.tokens.push(Token::new(
-                TokenType::SEMICOLON,
-                self.line,
-                None,
-                Object::None,
-            )),
-            '*' => self
-                .tokens
-                .push(Token::new(TokenType::STAR, self.line, None, Object::None)),
-            '-' => self
-                .tokens
-                .push(Token::new(TokenType::MINUS, self.line, None, Object::None)),
-            '+' => self
-                .tokens
-                .push(Token::new(TokenType::PLUS, self.line, None, Object::None)),
+            '{' => {
+                if let Some(i) = self.interpolations.last_mut() {
+                    i.depth += 1;
+                }
+                self.add_token(TokenType::LEFTBRACE, Object::None);
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(i) if i.depth == 0 => {
+                    let triple = i.triple;
+                    self.interpolations.pop();
+                    self.string_body(false, triple);
+                }
+                Some(i) => {
+                    i.depth -= 1;
+                    self.add_token(TokenType::RIGHTBRACE, Object::None);
+                }
+                None => self.add_token(TokenType::RIGHTBRACE, Object::None),
+            },
+            '(' => self.add_token(TokenType::LEFTPAREN, Object::None),
+            ')' => self.add_token(TokenType::RIGHTPAREN, Object::None),
+            '[' => self.add_token(TokenType::LEFTBRACKET, Object::None),
+            ']' => self.add_token(TokenType::RIGHTBRACKET, Object::None),
+            ',' => self.add_token(TokenType::COMMA, Object::None),
+            ':' => self.add_token(TokenType::COLON, Object::None),
+            '.' => self.add_token(TokenType::DOT, Object::None),
+            ';' => self.add_token(TokenType::SEMICOLON, Object::None),
+            '*' => self.add_token(TokenType::STAR, Object::None),
+            '-' => self.add_token(TokenType::MINUS, Object::None),
+            '+' => self.add_token(TokenType::PLUS, Object::None),
             '/' => {
-                if let Some(c) = self.source.peek() {
-                    if *c == '/' {
-                        while self.source.peek() != None && *self.source.peek().unwrap() != '\n' {
-                            self.source.next();
-                        }
-                        if self.source.peek() != None {
-                            self.line += 1;
-                            self.source.next();
-                        }
-                    } else {
-                        self.tokens.push(Token::new(
-                            TokenType::SLASH,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
+                if self.matches('/') {
+                    // `///` starts a doc comment, but `////` is a plain one.
+                    let doc = self.matches('/') && self.source.peek() != Some(&'/');
+                    while self.source.peek().is_some() && *self.source.peek().unwrap() != '\n' {
+                        self.advance();
+                    }
+                    if doc {
+                        self.doc_comment();
                     }
+                } else if self.matches('*') {
+                    self.block_comment();
                 } else {
-                    self.tokens
-                        .push(Token::new(TokenType::SLASH, self.line, None, Object::None));
+                    self.add_token(TokenType::SLASH, Object::None);
                 }
             }
             '!' => {
-                if let Some(c) = self.source.peek() {
-                    if *c == '=' {
-                        self.tokens.push(Token::new(
-                            TokenType::BANGEQUAL,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                        self.source.next();
-                    } else {
-                        self.tokens.push(Token::new(
-                            TokenType::BANG,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                    }
+                let token_type = if self.matches('=') {
+                    TokenType::BANGEQUAL
                 } else {
-                    self.tokens
-                        .push(Token::new(TokenType::BANG, self.line, None, Object::None));
-                }
+                    TokenType::BANG
+                };
+                self.add_token(token_type, Object::None);
             }
-
             '=' => {
-                if let Some(c) = self.source.peek() {
-                    if *c == '=' {
-                        self.tokens.push(Token::new(
-                            TokenType::EQUALEQUAL,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                        self.source.next();
-                    } else {
-                        self.tokens.push(Token::new(
-                            TokenType::EQUAL,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                    }
+                let token_type = if self.matches('=') {
+                    TokenType::EQUALEQUAL
+                } else if self.matches('>') {
+                    TokenType::ARROW
                 } else {
-                    self.tokens
-                        .push(Token::new(TokenType::EQUAL, self.line, None, Object::None));
-                }
+                    TokenType::EQUAL
+                };
+                self.add_token(token_type, Object::None);
             }
-
             '>' => {
-                if let Some(c) = self.source.peek() {
-                    if *c == '=' {
-                        self.tokens.push(Token::new(
-                            TokenType::GREATEREQUAL,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                        self.source.next();
-                    } else {
-                        self.tokens.push(Token::new(
-                            TokenType::GREATER,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                    }
+                let token_type = if self.matches('=') {
+                    TokenType::GREATEREQUAL
                 } else {
-                    self.tokens.push(Token::new(
-                        TokenType::GREATER,
-                        self.line,
-                        None,
-                        Object::None,
-                    ));
-                }
+                    TokenType::GREATER
+                };
+                self.add_token(token_type, Object::None);
             }
             '<' => {
-                if let Some(c) = self.source.peek() {
-                    if *c == '=' {
-                        self.tokens.push(Token::new(
-                            TokenType::LESSEQUAL,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                        self.source.next();
-                    } else {
-                        self.tokens.push(Token::new(
-                            TokenType::LESS,
-                            self.line,
-                            None,
-                            Object::None,
-                        ));
-                    }
+                let token_type = if self.matches('=') {
+                    TokenType::LESSEQUAL
                 } else {
-                    self.tokens
-                        .push(Token::new(TokenType::LESS, self.line, None, Object::None));
+                    TokenType::LESS
+                };
+                self.add_token(token_type, Object::None);
+            }
+            '"' => self.string(false),
+            'r' if self.matches('"') => self.string(true),
+            '0'..='9' => self.number(),
+            '_' | 'a'..='z' | 'A'..='Z' => {
+                while self.source.peek().is_some()
+                    && Scanner::is_alpha_numeric(*self.source.peek().unwrap())
+                {
+                    self.advance();
+                }
+                match KEYWORDS.get(self.lexeme.as_str()) {
+                    Some(k) => self.add_token(k.clone(), Object::None),
+                    None => self.add_token(TokenType::IDENTIFIER, Object::None),
                 }
             }
-            '"' => {
-                let mut buf = String::new();
-                while !self.is_at_end() && *self.source.peek().unwrap() != '"' {
-                    if *self.source.peek().unwrap() == '\n' {
-                        self.line += 1;
-                    } else {
-                        buf.push(*self.source.peek().unwrap());
-                    }
+            ' ' | '\t' | '\r' | '\n' => (),
+            _ => self.error("Unexpected character."),
+        }
+    }
 
-                    self.source.next();
-                }
+    /// Adds the text of the `///` comment just scanned to the pending doc.
+    fn doc_comment(&mut self) {
+        let text = self.lexeme[3..].trim_end();
+        let text = text.strip_prefix(' ').unwrap_or(text).to_string();
+        match &mut self.doc {
+            Some(doc) => {
+                doc.push('\n');
+                doc.push_str(&text);
+            }
+            None => self.doc = Some(text),
+        }
+    }
 
-                if self.is_at_end() {
-                    self.error(self.line, "Unterminated string.");
-                } else {
-                    self.source.next();
-
-                    self.tokens.push(Token::new(
-                        TokenType::STRING,
-                        self.line,
-                        Some(buf.clone()),
-                        Object::Str(buf),
-                    ));
-                }
+    /// Skips a block comment whose `/*` has been consumed. Block comments nest.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error("Unterminated block comment.");
+                return;
             }
-            '0'..='9' => {
-                let mut buf = String::from(c);
-                while self.source.peek() != None
-                    && ((*self.source.peek().unwrap()).is_digit(10)
-                        || *self.source.peek().unwrap() == '.')
-                {
-                    buf.push(*self.source.peek().unwrap());
-                    self.source.next();
-                }
-                self.tokens.push(Token::new(
-                    TokenType::NUMBER,
-                    self.line,
-                    Some(buf.clone()),
-                    Object::Num(f64::from_str(&buf).unwrap()),
-                ));
+
+            match self.advance() {
+                '/' if self.matches('*') => depth += 1,
+                '*' if self.matches('/') => depth -= 1,
+                _ => (),
             }
-            '_' | 'a'..='z' | 'A'..='Z' => {
-                let mut buf = String::from(c);
-                while self.source.peek() != None
-                    && Scanner::is_alpha_numeric(*self.source.peek().unwrap())
-                {
-                    buf.push(*self.source.peek().unwrap());
-                    self.source.next();
-                }
-                match KEYWORDS.get(buf.as_str()) {
-                    Some(k) => {
-                        let lexeme = match k {
-                            TokenType::THIS => Some("this".to_string()),
-                            _ => None,
-                        };
-                        self.tokens
-                            .push(Token::new(k.clone(), self.line, lexeme, Object::None))
+        }
+    }
+
+    /// Scans a number literal. Everything that could be part of one is taken
+    /// first, so that e.g. `1.2.3` is reported as a whole rather than split.
+    fn number(&mut self) {
+        while let Some(&c) = self.source.peek() {
+            let exponent_sign = (c == '+' || c == '-')
+                && (self.lexeme.ends_with('e') || self.lexeme.ends_with('E'))
+                && !self.lexeme.starts_with("0x")
+                && !self.lexeme.starts_with("0X");
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || exponent_sign) {
+                break;
+            }
+            self.advance();
+        }
+
+        match parse_number(&self.lexeme) {
+            Ok(value) => self.add_token(TokenType::NUMBER, Object::Num(value)),
+            Err(message) => self.error(&message),
+        }
+    }
+
+    /// Scans a string literal whose opening quote has been consumed. Three
+    /// quotes open a multi-line string, and raw strings skip escape processing.
+    fn string(&mut self, raw: bool) {
+        let mut triple = false;
+        if self.matches('"') {
+            if !self.matches('"') {
+                self.add_token(TokenType::STRING, Object::Str(String::new()));
+                return;
+            }
+            triple = true;
+        }
+
+        self.string_body(raw, triple);
+    }
+
+    /// Scans string characters up to the closing quotes, or up to a `${`
+    /// that starts an interpolated expression.
+    fn string_body(&mut self, raw: bool, triple: bool) {
+        let mut buf = String::new();
+        loop {
+            if self.is_at_end() {
+                self.error("Unterminated string.");
+                return;
+            }
+
+            match self.advance() {
+                '"' if !triple => break,
+                '"' => {
+                    if !self.matches('"') {
+                        buf.push('"');
+                    } else if self.matches('"') {
+                        break;
+                    } else {
+                        buf.push_str("\"\"");
                     }
-                    None => self.tokens.push(Token::new(
-                        TokenType::IDENTIFIER,
-                        self.line,
-                        Some(buf),
-                        Object::None,
-                    )),
                 }
+                '\\' if !raw => self.escape(&mut buf),
+                '$' if !raw && self.matches('{') => {
+                    self.interpolations.push(Interpolation {
+                        depth: 0,
+                        triple,
+                        start: self.current - 2,
+                        line: self.line,
+                        column: self.column - 2,
+                    });
+                    self.add_token(TokenType::INTERPOLATION, Object::Str(buf));
+                    return;
+                }
+                c => buf.push(c),
             }
-            ' ' | '\t' | '\r' => (),
-            '\n' => self.line += 1,
-            _ => self.error(self.line, "Unexpected character."),
         }
+
+        self.add_token(TokenType::STRING, Object::Str(buf));
+    }
+
+    /// Scans the rest of an escape sequence whose `\\` has been consumed.
+    fn escape(&mut self, buf: &mut String) {
+        let (start, line, column) = (self.current - 1, self.line, self.column - 1);
+        if self.is_at_end() {
+            return;
+        }
+
+        match self.advance() {
+            'n' => buf.push('\n'),
+            't' => buf.push('\t'),
+            '"' => buf.push('"'),
+            '\\' => buf.push('\\'),
+            '$' => buf.push('$'),
+            'u' => match self.unicode_escape() {
+                Some(c) => buf.push(c),
+                None => self.error_at(start, line, column, "Invalid unicode escape."),
+            },
+            c => self.error_at(
+                start,
+                line,
+                column,
+                &format!("Unknown escape sequence '\\{}'.", c),
+            ),
+        }
+    }
+
+    /// Scans the `{...}` of a `\\u{...}` escape, holding 1 to 6 hex digits.
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.matches('{') {
+            return None;
+        }
+
+        let mut digits = String::new();
+        while let Some(&c) = self.source.peek()
+            && c.is_ascii_hexdigit()
+        {
+            digits.push(self.advance());
+        }
+
+        if !self.matches('}') || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+
+        char::from_u32(u32::from_str_radix(&digits, 16).ok()?)
+    }
+
+    fn advance(&mut self) -> char {
+        let c = self.source.next().unwrap();
+        self.current += c.len_utf8();
+        self.lexeme.push(c);
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        c
+    }
+
+    fn matches(&mut self, expected: char) -> bool {
+        if self.source.peek() == Some(&expected) {
+            self.advance();
+            return true;
+        }
+
+        false
+    }
+
+    fn add_token(&mut self, token_type: TokenType, literal: Object) {
+        let mut token = Token::new(
+            token_type,
+            self.lexeme.clone(),
+            literal,
+            self.start_line,
+            self.start_column,
+            Span::new(self.start, self.current),
+        );
+        token.doc = self.doc.take();
+        self.tokens.push(token);
     }
 
     fn is_at_end(&mut self) -> bool {
-        self.source.peek() == None
+        self.source.peek().is_none()
     }
 }
+
+/// Parses the text of a number literal: a decimal number with an optional
+/// fraction and exponent, or an integer with a `0x`, `0b` or `0o` prefix.
+/// Digits may be separated with `_`.
+fn parse_number(text: &str) -> Result<f64, String> {
+    let (radix, name, digits) = match text.get(..2) {
+        Some("0x" | "0X") => (16, "hexadecimal", &text[2..]),
+        Some("0b" | "0B") => (2, "binary", &text[2..]),
+        Some("0o" | "0O") => (8, "octal", &text[2..]),
+        _ => (10, "decimal", text),
+    };
+
+    if radix != 10 {
+        if digits.is_empty() {
+            return Err(format!("Expect digits after '{}'.", &text[..2]));
+        }
+        if let Some(c) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+            return Err(format!("Invalid digit '{}' in {} literal.", c, name));
+        }
+        check_separators(digits)?;
+
+        return u64::from_str_radix(&digits.replace('_', ""), radix)
+            .map(|n| n as f64)
+            .map_err(|_| "Number literal is too large.".to_string());
+    }
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit() || c == '_');
+    if !is_digits(integer) {
+        return Err("Invalid number literal.".to_string());
+    }
+    check_separators(integer)?;
+
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || !fraction.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err("Expect digit after '.'.".to_string());
+        }
+        if !is_digits(fraction) {
+            return Err("Invalid number literal.".to_string());
+        }
+        check_separators(fraction)?;
+    }
+
+    if let Some(exponent) = exponent {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if digits.is_empty() || !is_digits(digits) {
+            return Err("Expect digits in exponent.".to_string());
+        }
+        check_separators(digits)?;
+    }
+
+    let value = f64::from_str(&text.replace('_', "")).unwrap();
+    if value.is_infinite() {
+        return Err("Number literal is too large.".to_string());
+    }
+
+    Ok(value)
+}
+
+/// Checks that every `_` in a run of digits sits between two digits.
+fn check_separators(digits: &str) -> Result<(), String> {
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err("Digit separators must be between digits.".to_string());
+    }
+
+    Ok(())
+}
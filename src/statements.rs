@@ -1,19 +1,24 @@
-use crate::expressions::Expr;
-use crate::scanner::Token;
+use crate::expressions::{Expr, Variable};
+use crate::scanner::{Span, Token};
+use std::rc::Rc;
 
 pub trait VisitorS<T> {
-    fn visit_expr_stmt(&mut self, stmt: &mut Expr) -> T;
-    fn visit_var_stmt(&mut self, stmt: &mut Var) -> T;
-    fn visit_block_stmt(&mut self, stmt: &mut Block) -> T;
-    fn visit_if_stmt(&mut self, stmt: &mut IfStmt) -> T;
-    fn visit_while_stmt(&mut self, stmt: &mut WhileStmt) -> T;
-    fn visit_func_stmt(&mut self, stmt: &mut Func) -> T;
-    fn visit_return_stmt(&mut self, stmt: &mut ReturnStmt) -> T;
-    fn visit_class_stmt(&mut self, stmt: &mut Class) -> T;
+    fn visit_expr_stmt(&mut self, stmt: &Expr) -> T;
+    fn visit_var_stmt(&mut self, stmt: &Var) -> T;
+    fn visit_block_stmt(&mut self, stmt: &Block) -> T;
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> T;
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> T;
+    fn visit_func_stmt(&mut self, stmt: &Func) -> T;
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> T;
+    fn visit_class_stmt(&mut self, stmt: &Class) -> T;
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) -> T;
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) -> T;
+    fn visit_throw_stmt(&mut self, stmt: &ThrowStmt) -> T;
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> T;
 }
 
 pub trait VisitableS<T> {
-    fn accept(&mut self, visitor: &mut impl VisitorS<T>) -> T;
+    fn accept(&self, visitor: &mut impl VisitorS<T>) -> T;
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -22,14 +27,37 @@ pub enum Stmt {
     Var(Var),
     Block(Block),
     If(IfStmt),
-    While(WhileStmt),
+    While(Box<WhileStmt>),
     Func(Func),
     Class(Class),
     Return(ReturnStmt),
+    Break(BreakStmt),
+    Continue(ContinueStmt),
+    Throw(ThrowStmt),
+    Try(TryStmt),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::ExprStmt(e) => e.span(),
+            Self::Var(v) => v.span,
+            Self::Block(b) => b.span,
+            Self::If(i) => i.span,
+            Self::While(w) => w.span,
+            Self::Func(f) => f.span,
+            Self::Return(r) => r.span,
+            Self::Class(c) => c.span,
+            Self::Break(b) => b.span,
+            Self::Continue(c) => c.span,
+            Self::Throw(t) => t.span,
+            Self::Try(t) => t.span,
+        }
+    }
 }
 
 impl<T> VisitableS<T> for Stmt {
-    fn accept(&mut self, visitor: &mut impl VisitorS<T>) -> T {
+    fn accept(&self, visitor: &mut impl VisitorS<T>) -> T {
         match self {
             Self::ExprStmt(e) => visitor.visit_expr_stmt(e),
             Self::Var(v) => visitor.visit_var_stmt(v),
@@ -39,36 +67,52 @@ impl<T> VisitableS<T> for Stmt {
             Self::Func(f) => visitor.visit_func_stmt(f),
             Self::Return(r) => visitor.visit_return_stmt(r),
             Self::Class(c) => visitor.visit_class_stmt(c),
+            Self::Break(b) => visitor.visit_break_stmt(b),
+            Self::Continue(c) => visitor.visit_continue_stmt(c),
+            Self::Throw(t) => visitor.visit_throw_stmt(t),
+            Self::Try(t) => visitor.visit_try_stmt(t),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Class {
+    pub span: Span,
     pub name: Token,
+    pub superclass: Option<Variable>,
     pub methods: Vec<Func>,
+    /// The `///` comments documenting the declaration.
+    pub doc: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Func {
+    pub span: Span,
     pub name: Token,
-    pub params: Vec<Token>,
-    pub body: Vec<Stmt>,
+    /// The parameters and body are shared with every function value made
+    /// from the declaration.
+    pub params: Rc<[Token]>,
+    pub body: Rc<[Stmt]>,
+    pub doc: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Var {
+    pub span: Span,
     pub token: Token,
     pub initializer: Option<Expr>,
+    pub doc: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Block {
+    pub span: Span,
     pub stmts: Vec<Stmt>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct IfStmt {
+    pub span: Span,
     pub condition: Expr,
     pub then_block: Box<Stmt>,
     pub else_block: Option<Box<Stmt>>,
@@ -76,12 +120,50 @@ pub struct IfStmt {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct WhileStmt {
+    pub span: Span,
     pub condition: Expr,
     pub body: Box<Stmt>,
+    // Only set for desugared `for` loops, so that `continue` still runs it.
+    pub increment: Option<Expr>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ReturnStmt {
+    pub span: Span,
     pub keyword: Token,
     pub value: Option<Expr>,
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakStmt {
+    pub span: Span,
+    pub keyword: Token,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContinueStmt {
+    pub span: Span,
+    pub keyword: Token,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThrowStmt {
+    pub span: Span,
+    pub keyword: Token,
+    pub value: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TryStmt {
+    pub span: Span,
+    pub body: Vec<Stmt>,
+    pub catch: Option<CatchClause>,
+    pub finally: Option<Vec<Stmt>>,
+}
+
+/// The `catch (name) { ... }` part of a `try` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CatchClause {
+    pub name: Token,
+    pub body: Vec<Stmt>,
+}
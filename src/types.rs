@@ -1,79 +1,164 @@
-use crate::interpreter::{Environment, Interpreter, RuntimeError};
+use crate::collections::{
+    Collection, Element, ListMethod, LoxMap, MapMethod, eq_collections, fmt_collection,
+};
+use crate::gc::{Gc, Heap};
+use crate::interpreter::{ControlFlow, Environment, Interpreter, RuntimeError};
 use crate::scanner::Token;
 use crate::statements::Stmt;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::io::{self, Write};
+use std::ops::RangeInclusive;
 use std::{cell::RefCell, rc::Rc};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Function {
     pub name: String,
     pub is_init: bool,
-    pub body: Vec<Stmt>,
-    pub params: Vec<Token>,
-    pub closure: Rc<RefCell<Environment>>,
+    pub body: Rc<[Stmt]>,
+    pub params: Rc<[Token]>,
+    /// `None` for functions declared at the top level.
+    pub closure: Option<Gc<Environment>>,
+    /// For a bound method, the instance it is bound to and the method it was
+    /// made from.
+    pub bound: Option<(InstanceRef, Rc<Function>)>,
 }
 
 impl Function {
-    pub fn bind(&self, instance: LoxInstance) -> Self {
-        let mut environment = Environment::new(Some(self.closure.clone()));
-        environment.set("this".to_string(), Object::ClassInstance(instance));
+    pub fn bind(self: &Rc<Self>, interpreter: &mut Interpreter, instance: InstanceRef) -> Self {
+        let environment =
+            Environment::with_slots(vec![Object::ClassInstance(instance.clone())], self.closure);
+        let environment = interpreter.alloc(environment);
 
         Function {
             name: self.name.clone(),
             is_init: self.is_init,
             body: self.body.clone(),
             params: self.params.clone(),
-            closure: Rc::new(RefCell::new(environment)),
+            closure: Some(environment),
+            bound: Some((instance, self.clone())),
+        }
+    }
+
+    /// The name used for the function in stack traces, e.g. `Point.init`.
+    pub fn qualified_name(&self) -> String {
+        match &self.bound {
+            Some((instance, _)) => format!("{}.{}", instance.class_name, self.name),
+            None => self.name.clone(),
         }
     }
 
     pub fn call(
-        &mut self,
+        &self,
         interpreter: &mut Interpreter,
         arguments: Vec<Object>,
     ) -> Result<Object, RuntimeError> {
-        let env = Rc::new(RefCell::new(Environment::new(Some(self.closure.clone()))));
+        // The parameters take the first slots.
+        let env = interpreter.alloc(Environment::with_slots(arguments, self.closure));
 
-        for i in 0..self.params.len() {
-            env.borrow_mut()
-                .values
-                .insert(self.params[i].clone().lexeme.unwrap(), arguments[i].clone());
+        let result = interpreter.execute_block(&self.body, env)?;
+        if self.is_init {
+            // Initializers are bound, so `this` is alone in the closure.
+            let closure = interpreter.heap.get(self.closure.unwrap());
+            return Ok(closure.slots[0].clone());
         }
 
-        let result = interpreter.execute_block(&mut self.body, env.clone())?;
-        if self.is_init {
-            return Ok(self.closure.borrow().values.get("this").unwrap().clone());
+        match result {
+            Some(ControlFlow::Return(value)) => Ok(value),
+            _ => Ok(Object::None),
         }
+    }
+}
+
+/// Anything that Lox code can call.
+pub trait Callable {
+    /// The smallest and largest number of arguments accepted.
+    fn arity(&self) -> RangeInclusive<usize>;
 
-        Ok(result.unwrap_or(Object::None))
+    /// Checks that a call passes an acceptable number of arguments,
+    /// reporting the error at the call's closing parenthesis.
+    fn check_arity(&self, count: usize, paren: &Token) -> Result<(), RuntimeError> {
+        check_arity(self.arity(), count, paren)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Checks that `count` arguments fit `arity`, for callables whose arity
+/// can't be known without the heap, e.g. classes with an inherited `init`.
+pub fn check_arity(
+    arity: RangeInclusive<usize>,
+    count: usize,
+    paren: &Token,
+) -> Result<(), RuntimeError> {
+    if arity.contains(&count) {
+        return Ok(());
+    }
+
+    let expected = if arity.start() == arity.end() {
+        arity.start().to_string()
+    } else {
+        format!("{} to {}", arity.start(), arity.end())
+    };
+    Err(RuntimeError::new(
+        format!("Expected {} arguments but got {}.", expected, count),
+        paren.clone(),
+    ))
+}
+
+impl Callable for Function {
+    fn arity(&self) -> RangeInclusive<usize> {
+        self.params.len()..=self.params.len()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum NativeFunc {
     INPUT,
     PRINTLN,
     PRINT,
+    GCSTATS,
+    LIST(Rc<RefCell<Vec<Object>>>, ListMethod),
+    MAP(Rc<RefCell<LoxMap<Object>>>, MapMethod),
+}
+
+impl PartialEq for NativeFunc {
+    /// List and map methods are equal if they are the same method of the
+    /// same list or map.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::LIST(l, a), Self::LIST(r, b)) => Rc::ptr_eq(l, r) && a == b,
+            (Self::MAP(l, a), Self::MAP(r, b)) => Rc::ptr_eq(l, r) && a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Callable for NativeFunc {
+    fn arity(&self) -> RangeInclusive<usize> {
+        match self {
+            // input() takes an optional prompt.
+            Self::INPUT => 0..=1,
+            Self::PRINTLN | Self::PRINT => 0..=usize::MAX,
+            Self::GCSTATS => 0..=0,
+            Self::LIST(_, method) => method.arity()..=method.arity(),
+            Self::MAP(_, method) => method.arity()..=method.arity(),
+        }
+    }
 }
 
 impl NativeFunc {
-    pub fn call(&mut self, name: Token, params: Vec<Object>) -> Result<Object, RuntimeError> {
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        name: Token,
+        params: Vec<Object>,
+    ) -> Result<Object, RuntimeError> {
         match self {
             Self::INPUT => {
-                // Optionally accept a single string prompt: input("prompt")
-                if params.len() > 1 {
-                    return Err(RuntimeError::new(
-                        format!("expect 0 or 1 arguments, got {}", params.len()),
-                        name,
-                    ));
-                }
-
                 // If user provided a prompt to input(), print it without newline and flush.
                 if params.len() == 1 {
                     // We only accept string prompts here; adjust if your Object type differs.
-                    print!("{}", params[0].to_string());
+                    print!("{}", params[0]);
                     if let Err(e) = io::stdout().flush() {
                         return Err(RuntimeError::new(e.to_string(), name));
                     }
@@ -111,57 +196,97 @@ impl NativeFunc {
                 }
                 Ok(Object::None)
             }
+
+            Self::GCSTATS => {
+                let stats = interpreter.heap.stats();
+                let mut map = LoxMap::new();
+                for (key, value) in [
+                    ("collections", stats.collections),
+                    ("allocated", stats.allocated),
+                    ("freed", stats.freed),
+                    ("live", stats.live),
+                ] {
+                    map.insert(Object::Str(key.to_string()), Object::Num(value as f64));
+                }
+                Ok(Object::Map(Rc::new(RefCell::new(map))))
+            }
+
+            Self::LIST(list, method) => method.call(&mut list.borrow_mut(), &params, &name),
+            Self::MAP(map, method) => method.call(&mut map.borrow_mut(), &params, &name),
+        }
+    }
+}
+
+/// A class, as stored on the heap.
+#[derive(Debug)]
+pub struct LoxClass {
+    pub(crate) superclass: Option<Gc<LoxClass>>,
+    pub(crate) methods: HashMap<String, Rc<Function>>,
+}
+
+impl LoxClass {
+    pub fn new(superclass: Option<Gc<LoxClass>>, methods: HashMap<String, Rc<Function>>) -> Self {
+        Self {
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks `name` up in the class, then in each of its superclasses.
+    pub fn find_method(&self, heap: &Heap, name: &str) -> Option<Rc<Function>> {
+        match self.methods.get(name) {
+            Some(f) => Some(f.clone()),
+            _ => heap.get(self.superclass?).find_method(heap, name),
         }
     }
 }
 
+/// A reference to a class on the heap. The name is kept alongside so that
+/// classes and their instances can be printed without the heap.
 #[derive(Debug, Clone, PartialEq)]
+pub struct ClassRef {
+    pub name: Rc<str>,
+    pub gc: Gc<LoxClass>,
+}
+
+/// An instance, as stored on the heap.
+#[derive(Debug)]
 pub struct LoxInstance {
-    klass: LoxClass,
-    fields: Rc<RefCell<HashMap<String, Object>>>,
+    pub(crate) klass: ClassRef,
+    pub(crate) fields: HashMap<String, Object>,
 }
 
 impl LoxInstance {
-    pub fn new(klass: LoxClass) -> Self {
+    pub fn new(klass: ClassRef) -> Self {
         Self {
             klass,
-            fields: Rc::new(RefCell::new(HashMap::new())),
+            fields: HashMap::new(),
         }
     }
 
-    pub fn get(&self, key: Token) -> Result<Object, RuntimeError> {
-        match self.fields.borrow().get(&key.lexeme.clone().unwrap()) {
-            Some(v) => Ok(v.clone()),
-            _ => match self.klass.find_method(key.lexeme.clone().unwrap()) {
-                Some(m) => Ok(Object::Func(m.bind(self.clone()))),
-                _ => Err(RuntimeError::new(
-                    format!("Undefined property {}", key.lexeme.clone().unwrap()),
-                    key,
-                )),
-            },
+    /// The message of an `Error` object, or `None` for other instances.
+    pub fn error_message(&self) -> Option<String> {
+        if &*self.klass.name != "Error" {
+            return None;
+        }
+
+        match self.fields.get("message") {
+            Some(Object::Str(message)) => Some(message.clone()),
+            _ => None,
         }
     }
 
     pub fn set(&mut self, key: String, value: Object) -> Result<Object, RuntimeError> {
-        self.fields.borrow_mut().insert(key, value.clone());
+        self.fields.insert(key, value.clone());
         Ok(value)
     }
 }
 
+/// A reference to an instance on the heap. Instances compare by identity.
 #[derive(Debug, Clone, PartialEq)]
-pub struct LoxClass {
-    name: String,
-    methods: HashMap<String, Function>,
-}
-
-impl LoxClass {
-    pub fn new(name: String, methods: HashMap<String, Function>) -> Self {
-        Self { name, methods }
-    }
-
-    pub fn find_method(&self, name: String) -> Option<Function> {
-        self.methods.get(&name).map(|f| f.clone())
-    }
+pub struct InstanceRef {
+    pub class_name: Rc<str>,
+    pub gc: Gc<LoxInstance>,
 }
 
 #[derive(Debug, Clone)]
@@ -169,10 +294,12 @@ pub enum Object {
     Num(f64),
     Str(String),
     Bool(bool),
-    Func(Function),
+    Func(Rc<Function>),
     NativeFunc(NativeFunc),
-    Class(LoxClass),
-    ClassInstance(LoxInstance),
+    Class(ClassRef),
+    ClassInstance(InstanceRef),
+    List(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<LoxMap<Object>>>),
     None,
 }
 
@@ -186,41 +313,109 @@ impl Object {
     }
 }
 
+impl Element for Object {
+    fn nil() -> Self {
+        Self::None
+    }
+
+    fn boolean(b: bool) -> Self {
+        Self::Bool(b)
+    }
+
+    fn number(n: f64) -> Self {
+        Self::Num(n)
+    }
+
+    fn list(elements: Vec<Self>) -> Self {
+        Self::List(Rc::new(RefCell::new(elements)))
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Num(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn is_primitive(&self) -> bool {
+        matches!(
+            self,
+            Self::Num(_) | Self::Str(_) | Self::Bool(_) | Self::None
+        )
+    }
+
+    fn collection(&self) -> Option<Collection<'_, Self>> {
+        match self {
+            Self::List(l) => Some(Collection::List(l)),
+            Self::Map(m) => Some(Collection::Map(m)),
+            _ => None,
+        }
+    }
+}
+
 impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         match self {
             Self::Num(l) => match other {
-                Self::Num(r) => return l == r,
-                _ => return false,
+                Self::Num(r) => l == r,
+                _ => false,
             },
             Self::Str(l) => match other {
-                Self::Str(r) => return l == r,
-                _ => return false,
+                Self::Str(r) => l == r,
+                _ => false,
             },
             Self::Bool(l) => match other {
-                Self::Bool(r) => return l == r,
-                _ => return false,
+                Self::Bool(r) => l == r,
+                _ => false,
             },
+            // Functions are equal only to themselves, and bound methods if
+            // they bind the same method to the same instance.
             Self::Func(l) => match other {
-                Self::Func(r) => return l == r,
-                _ => return false,
-            },
-            Self::None => match other {
-                Self::None => return true,
-                _ => return false,
+                Self::Func(r) => match (&l.bound, &r.bound) {
+                    (Some((a, m)), Some((b, n))) => a == b && Rc::ptr_eq(m, n),
+                    _ => Rc::ptr_eq(l, r),
+                },
+                _ => false,
             },
+            Self::None => matches!(other, Self::None),
             Self::NativeFunc(l) => match other {
-                Self::NativeFunc(r) => return l == r,
-                _ => return false,
+                Self::NativeFunc(r) => l == r,
+                _ => false,
             },
             Self::Class(l) => match other {
-                Self::Class(r) => return l == r,
-                _ => return false,
+                Self::Class(r) => l == r,
+                _ => false,
             },
             Self::ClassInstance(l) => match other {
-                Self::ClassInstance(r) => return l == r,
-                _ => return false,
+                Self::ClassInstance(r) => l == r,
+                _ => false,
             },
+            Self::List(l) => match other {
+                Self::List(r) => eq_collections(Collection::List(l), Collection::List(r)),
+                _ => false,
+            },
+            Self::Map(l) => match other {
+                Self::Map(r) => eq_collections(Collection::Map(l), Collection::Map(r)),
+                _ => false,
+            },
+        }
+    }
+}
+
+// Only numbers, strings, booleans and nil are ever hashed (see `map_key`), and
+// NaN is rejected there, so equality is reflexive for every hashed value.
+impl Eq for Object {}
+
+impl Hash for Object {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            // -0.0 == 0.0, so both must hash the same.
+            Self::Num(n) if *n == 0.0 => 0.0f64.to_bits().hash(state),
+            Self::Num(n) => n.to_bits().hash(state),
+            Self::Str(s) => s.hash(state),
+            Self::Bool(b) => b.hash(state),
+            _ => (),
         }
     }
 }
@@ -234,7 +429,9 @@ impl fmt::Display for Object {
             Self::Func(_) => write!(f, "<user defined> fn"),
             Self::NativeFunc(_) => write!(f, "native fn"),
             Self::Class(c) => write!(f, "{}", c.name),
-            Self::ClassInstance(i) => write!(f, "{} instance", i.klass.name),
+            Self::ClassInstance(i) => write!(f, "{} instance", i.class_name),
+            Self::List(l) => fmt_collection(Collection::List(l), f),
+            Self::Map(m) => fmt_collection(Collection::Map(m), f),
             Self::None => write!(f, "nil"),
         }
     }
@@ -0,0 +1,176 @@
+use crate::scanner::Token;
+use crate::vm::value::Value;
+
+/// A bytecode instruction. Operands follow the opcode in the chunk: `u16`
+/// operands are big-endian, everything else is a single byte.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpCode {
+    /// Pushes the constant at the `u16` index.
+    CONSTANT,
+    NIL,
+    TRUE,
+    FALSE,
+    POP,
+    /// Local slots and upvalue indexes are a single byte.
+    GETLOCAL,
+    SETLOCAL,
+    /// Globals and properties take the `u16` index of their name constant.
+    GETGLOBAL,
+    DEFINEGLOBAL,
+    SETGLOBAL,
+    GETUPVALUE,
+    SETUPVALUE,
+    GETPROPERTY,
+    SETPROPERTY,
+    GETSUPER,
+    GETINDEX,
+    SETINDEX,
+    EQUAL,
+    GREATER,
+    GREATEREQUAL,
+    LESS,
+    LESSEQUAL,
+    ADD,
+    SUBTRACT,
+    MULTIPLY,
+    DIVIDE,
+    NOT,
+    NEGATE,
+    /// Jumps take a `u16` distance, forwards for `JUMP` and `JUMPIFFALSE`
+    /// and backwards for `LOOP`.
+    JUMP,
+    JUMPIFFALSE,
+    LOOP,
+    /// Takes the argument count as a byte.
+    CALL,
+    /// Takes the function constant, then an `is_local` byte and an index
+    /// byte for each of its upvalues.
+    CLOSURE,
+    CLOSEUPVALUE,
+    RETURN,
+    CLASS,
+    INHERIT,
+    METHOD,
+    /// Collection literals and interpolated strings take a `u16` count of
+    /// elements, entries or parts on the stack.
+    LIST,
+    MAP,
+    BUILDSTRING,
+    /// Checks that the value on top of the stack can be a map key, leaving
+    /// it there.
+    MAPKEY,
+    THROW,
+    /// Installs an exception handler at the forward `u16` distance. A `TRY`
+    /// handler receives the thrown value, a `TRYFINALLY` handler receives
+    /// the whole exception so that it can be rethrown unchanged.
+    TRY,
+    TRYFINALLY,
+    POPHANDLER,
+    RETHROW,
+}
+
+const OPCODES: [OpCode; 47] = [
+    OpCode::CONSTANT,
+    OpCode::NIL,
+    OpCode::TRUE,
+    OpCode::FALSE,
+    OpCode::POP,
+    OpCode::GETLOCAL,
+    OpCode::SETLOCAL,
+    OpCode::GETGLOBAL,
+    OpCode::DEFINEGLOBAL,
+    OpCode::SETGLOBAL,
+    OpCode::GETUPVALUE,
+    OpCode::SETUPVALUE,
+    OpCode::GETPROPERTY,
+    OpCode::SETPROPERTY,
+    OpCode::GETSUPER,
+    OpCode::GETINDEX,
+    OpCode::SETINDEX,
+    OpCode::EQUAL,
+    OpCode::GREATER,
+    OpCode::GREATEREQUAL,
+    OpCode::LESS,
+    OpCode::LESSEQUAL,
+    OpCode::ADD,
+    OpCode::SUBTRACT,
+    OpCode::MULTIPLY,
+    OpCode::DIVIDE,
+    OpCode::NOT,
+    OpCode::NEGATE,
+    OpCode::JUMP,
+    OpCode::JUMPIFFALSE,
+    OpCode::LOOP,
+    OpCode::CALL,
+    OpCode::CLOSURE,
+    OpCode::CLOSEUPVALUE,
+    OpCode::RETURN,
+    OpCode::CLASS,
+    OpCode::INHERIT,
+    OpCode::METHOD,
+    OpCode::LIST,
+    OpCode::MAP,
+    OpCode::BUILDSTRING,
+    OpCode::MAPKEY,
+    OpCode::THROW,
+    OpCode::TRY,
+    OpCode::TRYFINALLY,
+    OpCode::POPHANDLER,
+    OpCode::RETHROW,
+];
+
+impl OpCode {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        OPCODES.get(byte as usize).copied()
+    }
+}
+
+/// The bytecode of one function, with the constants it refers to.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    // The token each run of instructions was compiled from, keyed by the
+    // offset of the run's first byte. Runtime errors are reported at it.
+    tokens: Vec<(usize, Token)>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, byte: u8, token: &Token) {
+        match self.tokens.last() {
+            Some((_, t)) if t.span == token.span && t.line == token.line => (),
+            _ => self.tokens.push((self.code.len(), token.clone())),
+        }
+        self.code.push(byte);
+    }
+
+    /// Adds `value` to the constant pool, reusing an equal string or number
+    /// already there, and returns its index.
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        let existing = self.constants.iter().position(|c| match (c, &value) {
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Num(a), Value::Num(b)) => a.to_bits() == b.to_bits(),
+            _ => false,
+        });
+
+        existing.unwrap_or_else(|| {
+            self.constants.push(value);
+            self.constants.len() - 1
+        })
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// The token the instruction at `offset` was compiled from.
+    pub fn token(&self, offset: usize) -> &Token {
+        let i = self.tokens.partition_point(|(start, _)| *start <= offset);
+        &self.tokens[i.saturating_sub(1)].1
+    }
+}
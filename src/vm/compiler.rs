@@ -0,0 +1,876 @@
+use crate::error::LoxError;
+use crate::expressions::{
+    Assign, Binary, Call, Expr, Get, Grouping, Index, Interpolation, Lambda, List, Literal,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable, VisitableE, VisitorE,
+};
+use crate::scanner::{Span, Token, TokenType};
+use crate::statements::{
+    Block, BreakStmt, Class, ContinueStmt, Func, IfStmt, ReturnStmt, Stmt, ThrowStmt, TryStmt, Var,
+    VisitableS, VisitorS, WhileStmt,
+};
+use crate::types::Object;
+use crate::vm::chunk::{Chunk, OpCode};
+use crate::vm::value::{Function, Value};
+use std::rc::Rc;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    FUNCTION,
+    METHOD,
+    INITIALIZER,
+    SCRIPT,
+}
+
+struct Local {
+    name: String,
+    // `None` until the variable's initializer has been compiled.
+    depth: Option<usize>,
+    captured: bool,
+}
+
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+struct Loop {
+    depth: usize,
+    // How many `try` statements were open when the loop started.
+    tries: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// A `try` statement whose body or `catch` clause is being compiled.
+#[derive(Clone)]
+struct TryBlock {
+    // Whether a handler is installed that has to be removed when leaving.
+    handler: bool,
+    finally: Option<Vec<Stmt>>,
+}
+
+/// The function currently being compiled.
+struct State {
+    function: Function,
+    kind: FunctionType,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueRef>,
+    depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<TryBlock>,
+}
+
+impl State {
+    fn new(name: &str, kind: FunctionType) -> Self {
+        // Slot zero holds the function being called, or `this` in methods.
+        let slot = match kind {
+            FunctionType::METHOD | FunctionType::INITIALIZER => "this",
+            _ => "",
+        };
+
+        Self {
+            function: Function::new(name),
+            kind,
+            locals: vec![Local {
+                name: slot.to_string(),
+                depth: Some(0),
+                captured: false,
+            }],
+            upvalues: Vec::new(),
+            depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+        }
+    }
+}
+
+/// Compiles a resolved program into bytecode for the `Vm`.
+pub struct Compiler {
+    states: Vec<State>,
+    // The token the code being emitted comes from, used to report errors.
+    token: Token,
+    errors: Vec<LoxError>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            states: vec![State::new("script", FunctionType::SCRIPT)],
+            token: Token::new(
+                TokenType::EOF,
+                String::new(),
+                Object::None,
+                1,
+                1,
+                Span::default(),
+            ),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Compiles a whole program into the function for its top-level code,
+    /// reporting every error found rather than stopping at the first.
+    pub fn compile(mut self, stmts: &[Stmt]) -> Result<Function, Vec<LoxError>> {
+        self.block(stmts);
+        self.emit_return();
+
+        if self.errors.is_empty() {
+            Ok(self.states.pop().unwrap().function)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn state(&mut self) -> &mut State {
+        self.states.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().function.chunk
+    }
+
+    fn error(&mut self, message: &str) {
+        self.errors.push(LoxError::compile(&self.token, message));
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts.iter() {
+            stmt.accept(self);
+        }
+    }
+
+    fn scoped_block(&mut self, stmts: &[Stmt]) {
+        self.begin_scope();
+        self.block(stmts);
+        self.end_scope();
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        expr.accept(self)
+    }
+
+    fn emit(&mut self, byte: u8) {
+        let state = self.states.last_mut().unwrap();
+        state.function.chunk.write(byte, &self.token);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        self.emit(op as u8);
+    }
+
+    fn emit_u16(&mut self, value: u16) {
+        let [high, low] = value.to_be_bytes();
+        self.emit(high);
+        self.emit(low);
+    }
+
+    fn emit_with(&mut self, op: OpCode, operand: u16) {
+        self.emit_op(op);
+        self.emit_u16(operand);
+    }
+
+    /// Pushes what a `return` without a value gives back.
+    fn emit_return_value(&mut self) {
+        if self.state().kind == FunctionType::INITIALIZER {
+            self.emit_op(OpCode::GETLOCAL);
+            self.emit(0);
+        } else {
+            self.emit_op(OpCode::NIL);
+        }
+    }
+
+    fn emit_return(&mut self) {
+        self.emit_return_value();
+        self.emit_op(OpCode::RETURN);
+    }
+
+    /// Emits a jump with a placeholder distance, returning the offset of
+    /// the distance so `patch_jump` can fill it in.
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op(op);
+        self.emit_u16(u16::MAX);
+        self.chunk().code.len() - 2
+    }
+
+    /// Points the jump at `offset` to the next instruction.
+    fn patch_jump(&mut self, offset: usize) {
+        let distance = self.chunk().code.len() - offset - 2;
+        if distance > u16::MAX as usize {
+            self.error("Too much code to jump over.");
+        }
+
+        let [high, low] = (distance as u16).to_be_bytes();
+        let code = &mut self.chunk().code;
+        code[offset] = high;
+        code[offset + 1] = low;
+    }
+
+    fn emit_loop(&mut self, start: usize) {
+        self.emit_op(OpCode::LOOP);
+        let distance = self.chunk().code.len() - start + 2;
+        if distance > u16::MAX as usize {
+            self.error("Loop body too large.");
+        }
+        self.emit_u16(distance as u16);
+    }
+
+    fn constant(&mut self, value: Value) -> u16 {
+        let index = self.chunk().add_constant(value);
+        if index > u16::MAX as usize {
+            self.error("Too many constants in one chunk.");
+            return 0;
+        }
+        index as u16
+    }
+
+    fn name_constant(&mut self, name: &str) -> u16 {
+        self.constant(Value::Str(name.into()))
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state().depth -= 1;
+        let depth = self.state().depth;
+        self.pop_locals(depth);
+
+        let state = self.state();
+        while state.locals.last().is_some_and(|l| l.depth > Some(depth)) {
+            state.locals.pop();
+        }
+    }
+
+    /// Emits the code to discard every local deeper than `depth`, without
+    /// forgetting about them, as needed when jumping out of their scope.
+    fn pop_locals(&mut self, depth: usize) {
+        let captured = self
+            .state()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|l| l.depth > Some(depth))
+            .map(|l| l.captured)
+            .collect::<Vec<_>>();
+
+        for captured in captured {
+            if captured {
+                self.emit_op(OpCode::CLOSEUPVALUE);
+            } else {
+                self.emit_op(OpCode::POP);
+            }
+        }
+    }
+
+    fn add_local(&mut self, name: &str) {
+        if self.state().locals.len() > u8::MAX as usize {
+            self.error("Too many local variables in function.");
+            return;
+        }
+
+        self.state().locals.push(Local {
+            name: name.to_string(),
+            depth: None,
+            captured: false,
+        });
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if self.state().depth == 0 {
+            return;
+        }
+        self.add_local(&name.lexeme);
+    }
+
+    fn mark_initialized(&mut self) {
+        let state = self.state();
+        if state.depth == 0 {
+            return;
+        }
+        if let Some(local) = state.locals.last_mut() {
+            local.depth = Some(state.depth);
+        }
+    }
+
+    /// Finishes declaring a variable whose value is on top of the stack.
+    fn define_variable(&mut self, global: u16) {
+        if self.state().depth > 0 {
+            self.mark_initialized();
+            return;
+        }
+        self.emit_with(OpCode::DEFINEGLOBAL, global);
+    }
+
+    fn resolve_local(&self, state: usize, name: &str) -> Option<u8> {
+        self.states[state]
+            .locals
+            .iter()
+            .rposition(|l| l.name == name)
+            .map(|i| i as u8)
+    }
+
+    fn resolve_upvalue(&mut self, state: usize, name: &str) -> Option<u8> {
+        if state == 0 {
+            return None;
+        }
+
+        if let Some(local) = self.resolve_local(state - 1, name) {
+            self.states[state - 1].locals[local as usize].captured = true;
+            return Some(self.add_upvalue(state, local, true));
+        }
+
+        let upvalue = self.resolve_upvalue(state - 1, name)?;
+        Some(self.add_upvalue(state, upvalue, false))
+    }
+
+    fn add_upvalue(&mut self, state: usize, index: u8, is_local: bool) -> u8 {
+        let upvalues = &self.states[state].upvalues;
+        if let Some(i) = upvalues
+            .iter()
+            .position(|u| u.index == index && u.is_local == is_local)
+        {
+            return i as u8;
+        }
+
+        if upvalues.len() > u8::MAX as usize {
+            self.error("Too many closure variables in function.");
+            return 0;
+        }
+
+        let upvalues = &mut self.states[state].upvalues;
+        upvalues.push(UpvalueRef { index, is_local });
+        (upvalues.len() - 1) as u8
+    }
+
+    /// Loads the variable `name`, or stores the value on top of the stack in
+    /// it when `set` is true.
+    fn named_variable(&mut self, name: &Token, set: bool) {
+        self.token = name.clone();
+        let state = self.states.len() - 1;
+
+        if let Some(slot) = self.resolve_local(state, &name.lexeme) {
+            self.emit_op(if set {
+                OpCode::SETLOCAL
+            } else {
+                OpCode::GETLOCAL
+            });
+            self.emit(slot);
+        } else if let Some(index) = self.resolve_upvalue(state, &name.lexeme) {
+            self.emit_op(if set {
+                OpCode::SETUPVALUE
+            } else {
+                OpCode::GETUPVALUE
+            });
+            self.emit(index);
+        } else {
+            let global = self.name_constant(&name.lexeme);
+            self.emit_with(
+                if set {
+                    OpCode::SETGLOBAL
+                } else {
+                    OpCode::GETGLOBAL
+                },
+                global,
+            );
+        }
+    }
+
+    /// Compiles a function body and emits the closure for it.
+    fn function(&mut self, name: &str, params: &[Token], body: &[Stmt], kind: FunctionType) {
+        let token = self.token.clone();
+        self.states.push(State::new(name, kind));
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.mark_initialized();
+        }
+
+        self.block(body);
+        self.emit_return();
+
+        let state = self.states.pop().unwrap();
+        self.token = token;
+        let mut function = state.function;
+        function.arity = params.len();
+        function.upvalues = state.upvalues.len();
+
+        let index = self.constant(Value::Function(Rc::new(function)));
+        self.emit_with(OpCode::CLOSURE, index);
+        for upvalue in state.upvalues {
+            self.emit(upvalue.is_local as u8);
+            self.emit(upvalue.index);
+        }
+    }
+
+    /// Emits the code for leaving every `try` statement opened since `to`,
+    /// innermost first: removing its handler and running its `finally`.
+    fn exit_tries(&mut self, to: usize) {
+        for i in (to..self.state().tries.len()).rev() {
+            if self.state().tries[i].handler {
+                self.emit_op(OpCode::POPHANDLER);
+            }
+
+            if let Some(finally) = self.state().tries[i].finally.clone() {
+                // The `finally` block runs outside of the statements it is
+                // leaving, so hide them while compiling it.
+                let state = self.state();
+                let tries = state.tries.split_off(i);
+                let inner = state
+                    .loops
+                    .iter()
+                    .position(|l| l.tries > i)
+                    .unwrap_or(state.loops.len());
+                let loops = state.loops.split_off(inner);
+
+                self.scoped_block(&finally);
+
+                let state = self.state();
+                state.tries.extend(tries);
+                state.loops.extend(loops);
+            }
+        }
+    }
+
+    fn count(&mut self, count: usize, what: &str) -> u16 {
+        if count > u16::MAX as usize {
+            self.error(&format!("Too many {} in one expression.", what));
+        }
+        count as u16
+    }
+}
+
+impl VisitorS<()> for Compiler {
+    fn visit_expr_stmt(&mut self, stmt: &Expr) {
+        self.expression(stmt);
+        self.emit_op(OpCode::POP);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) {
+        let global = self.name_constant(&stmt.token.lexeme);
+        self.declare(&stmt.token);
+
+        match &stmt.initializer {
+            Some(e) => self.expression(e),
+            None => self.emit_op(OpCode::NIL),
+        }
+
+        self.token = stmt.token.clone();
+        self.define_variable(global);
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &Block) {
+        self.scoped_block(&stmt.stmts);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        self.expression(&stmt.condition);
+        let then_jump = self.emit_jump(OpCode::JUMPIFFALSE);
+        self.emit_op(OpCode::POP);
+        stmt.then_block.accept(self);
+
+        let else_jump = self.emit_jump(OpCode::JUMP);
+        self.patch_jump(then_jump);
+        self.emit_op(OpCode::POP);
+        if let Some(s) = &stmt.else_block {
+            s.accept(self);
+        }
+        self.patch_jump(else_jump);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        let start = self.chunk().code.len();
+        self.expression(&stmt.condition);
+        let exit = self.emit_jump(OpCode::JUMPIFFALSE);
+        self.emit_op(OpCode::POP);
+
+        let state = self.state();
+        state.loops.push(Loop {
+            depth: state.depth,
+            tries: state.tries.len(),
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+        stmt.body.accept(self);
+        let l = self.state().loops.pop().unwrap();
+
+        for jump in l.continues {
+            self.patch_jump(jump);
+        }
+        if let Some(i) = &stmt.increment {
+            self.expression(i);
+            self.emit_op(OpCode::POP);
+        }
+        self.emit_loop(start);
+
+        self.patch_jump(exit);
+        self.emit_op(OpCode::POP);
+        for jump in l.breaks {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn visit_func_stmt(&mut self, stmt: &Func) {
+        let global = self.name_constant(&stmt.name.lexeme);
+        self.declare(&stmt.name);
+        // The function can refer to itself, so it is defined up front.
+        self.mark_initialized();
+
+        self.token = stmt.name.clone();
+        self.function(
+            &stmt.name.lexeme,
+            &stmt.params,
+            &stmt.body,
+            FunctionType::FUNCTION,
+        );
+        self.token = stmt.name.clone();
+        self.define_variable(global);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        self.token = stmt.keyword.clone();
+        match &stmt.value {
+            Some(e) => self.expression(e),
+            None => self.emit_return_value(),
+        }
+
+        if !self.state().tries.is_empty() {
+            // Keep the value in a slot of its own while `finally` blocks run.
+            let state = self.state();
+            state.locals.push(Local {
+                name: String::new(),
+                depth: Some(state.depth),
+                captured: false,
+            });
+            self.exit_tries(0);
+            self.state().locals.pop();
+        }
+
+        self.token = stmt.keyword.clone();
+        self.emit_op(OpCode::RETURN);
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Class) {
+        let name = self.name_constant(&stmt.name.lexeme);
+        self.declare(&stmt.name);
+        self.token = stmt.name.clone();
+        self.emit_with(OpCode::CLASS, name);
+        self.define_variable(name);
+
+        if let Some(superclass) = &stmt.superclass {
+            self.named_variable(&superclass.name, false);
+            self.begin_scope();
+            self.add_local("super");
+            self.mark_initialized();
+
+            self.named_variable(&stmt.name, false);
+            self.token = superclass.name.clone();
+            self.emit_op(OpCode::INHERIT);
+        }
+
+        self.named_variable(&stmt.name, false);
+        for method in stmt.methods.iter() {
+            let kind = if method.name.lexeme == "init" {
+                FunctionType::INITIALIZER
+            } else {
+                FunctionType::METHOD
+            };
+            self.token = method.name.clone();
+            self.function(&method.name.lexeme, &method.params, &method.body, kind);
+
+            let name = self.name_constant(&method.name.lexeme);
+            self.emit_with(OpCode::METHOD, name);
+        }
+        self.emit_op(OpCode::POP);
+
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) {
+        self.token = stmt.keyword.clone();
+        let l = self.state().loops.last().unwrap();
+        let (depth, tries) = (l.depth, l.tries);
+
+        self.exit_tries(tries);
+        self.token = stmt.keyword.clone();
+        self.pop_locals(depth);
+        let jump = self.emit_jump(OpCode::JUMP);
+        self.state().loops.last_mut().unwrap().breaks.push(jump);
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) {
+        self.token = stmt.keyword.clone();
+        let l = self.state().loops.last().unwrap();
+        let (depth, tries) = (l.depth, l.tries);
+
+        self.exit_tries(tries);
+        self.token = stmt.keyword.clone();
+        self.pop_locals(depth);
+        let jump = self.emit_jump(OpCode::JUMP);
+        self.state().loops.last_mut().unwrap().continues.push(jump);
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &ThrowStmt) {
+        self.expression(&stmt.value);
+        self.token = stmt.keyword.clone();
+        self.emit_op(OpCode::THROW);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) {
+        let has_finally = stmt.finally.is_some();
+        self.state().tries.push(TryBlock {
+            handler: true,
+            finally: stmt.finally.clone(),
+        });
+
+        let handler = self.emit_jump(if stmt.catch.is_some() {
+            OpCode::TRY
+        } else {
+            OpCode::TRYFINALLY
+        });
+        self.scoped_block(&stmt.body);
+        self.emit_op(OpCode::POPHANDLER);
+
+        // The handler that runs `finally` for an exception, and the number of
+        // slots the stack has above the statement's when it does.
+        let mut rethrow = (handler, 1);
+        if let Some(catch) = &stmt.catch {
+            let skip = self.emit_jump(OpCode::JUMP);
+
+            // The handler left the thrown value on the stack, as the variable.
+            self.patch_jump(handler);
+            self.begin_scope();
+            self.declare(&catch.name);
+            self.mark_initialized();
+
+            if has_finally {
+                rethrow = (self.emit_jump(OpCode::TRYFINALLY), 2);
+            }
+            self.state().tries.last_mut().unwrap().handler = has_finally;
+            self.block(&catch.body);
+            if has_finally {
+                self.emit_op(OpCode::POPHANDLER);
+            }
+
+            self.end_scope();
+            self.patch_jump(skip);
+        }
+        self.state().tries.pop();
+
+        if let Some(finally) = &stmt.finally {
+            self.scoped_block(finally);
+            let end = self.emit_jump(OpCode::JUMP);
+
+            let (handler, slots) = rethrow;
+            self.patch_jump(handler);
+            self.begin_scope();
+            for _ in 0..slots {
+                self.add_local("");
+                self.mark_initialized();
+            }
+            self.scoped_block(finally);
+            self.emit_op(OpCode::RETHROW);
+
+            // Nothing runs after the rethrow, so the slots are just forgotten.
+            let state = self.state();
+            state.depth -= 1;
+            let len = state.locals.len();
+            state.locals.truncate(len - slots);
+            self.patch_jump(end);
+        }
+    }
+}
+
+impl VisitorE<()> for Compiler {
+    fn visit_binary(&mut self, expr: &Binary) {
+        self.expression(&expr.left);
+        self.expression(&expr.right);
+
+        self.token = expr.operator.clone();
+        match expr.operator.token_type {
+            TokenType::PLUS => self.emit_op(OpCode::ADD),
+            TokenType::MINUS => self.emit_op(OpCode::SUBTRACT),
+            TokenType::STAR => self.emit_op(OpCode::MULTIPLY),
+            TokenType::SLASH => self.emit_op(OpCode::DIVIDE),
+            TokenType::GREATER => self.emit_op(OpCode::GREATER),
+            TokenType::GREATEREQUAL => self.emit_op(OpCode::GREATEREQUAL),
+            TokenType::LESS => self.emit_op(OpCode::LESS),
+            TokenType::LESSEQUAL => self.emit_op(OpCode::LESSEQUAL),
+            TokenType::EQUALEQUAL => self.emit_op(OpCode::EQUAL),
+            TokenType::BANGEQUAL => {
+                self.emit_op(OpCode::EQUAL);
+                self.emit_op(OpCode::NOT);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn visit_unary(&mut self, expr: &Unary) {
+        self.expression(&expr.right);
+
+        self.token = expr.operator.clone();
+        match expr.operator.token_type {
+            TokenType::MINUS => self.emit_op(OpCode::NEGATE),
+            TokenType::BANG => self.emit_op(OpCode::NOT),
+            _ => unreachable!(),
+        }
+    }
+
+    fn visit_grouping(&mut self, expr: &Grouping) {
+        self.expression(&expr.expr);
+    }
+
+    fn visit_literal(&mut self, expr: &Literal) {
+        match &expr.value {
+            Object::None => self.emit_op(OpCode::NIL),
+            Object::Bool(true) => self.emit_op(OpCode::TRUE),
+            Object::Bool(false) => self.emit_op(OpCode::FALSE),
+            value => {
+                let index = self.constant(Value::from(value));
+                self.emit_with(OpCode::CONSTANT, index);
+            }
+        }
+    }
+
+    fn visit_variable(&mut self, expr: &Variable) {
+        self.named_variable(&expr.name, false);
+    }
+
+    fn visit_assign(&mut self, expr: &Assign) {
+        self.expression(&expr.value);
+        self.named_variable(&expr.name, true);
+    }
+
+    fn visit_logical(&mut self, expr: &Logical) {
+        self.expression(&expr.left);
+
+        self.token = expr.operator.clone();
+        match expr.operator.token_type {
+            TokenType::OR => {
+                let else_jump = self.emit_jump(OpCode::JUMPIFFALSE);
+                let end = self.emit_jump(OpCode::JUMP);
+                self.patch_jump(else_jump);
+                self.emit_op(OpCode::POP);
+                self.expression(&expr.right);
+                self.patch_jump(end);
+            }
+            TokenType::AND => {
+                let end = self.emit_jump(OpCode::JUMPIFFALSE);
+                self.emit_op(OpCode::POP);
+                self.expression(&expr.right);
+                self.patch_jump(end);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn visit_call(&mut self, expr: &Call) {
+        self.expression(&expr.calle);
+        for arg in expr.arguments.iter() {
+            self.expression(arg);
+        }
+
+        self.token = expr.paren.clone();
+        if expr.arguments.len() > u8::MAX as usize {
+            self.error("Can't have more than 255 arguments.");
+        }
+        self.emit_op(OpCode::CALL);
+        self.emit(expr.arguments.len() as u8);
+    }
+
+    fn visit_get(&mut self, expr: &Get) {
+        self.expression(&expr.expr);
+
+        self.token = expr.name.clone();
+        let name = self.name_constant(&expr.name.lexeme);
+        self.emit_with(OpCode::GETPROPERTY, name);
+    }
+
+    fn visit_set(&mut self, expr: &Set) {
+        self.expression(&expr.expr);
+        self.expression(&expr.value);
+
+        self.token = expr.name.clone();
+        let name = self.name_constant(&expr.name.lexeme);
+        self.emit_with(OpCode::SETPROPERTY, name);
+    }
+
+    fn visit_this(&mut self, expr: &This) {
+        self.named_variable(&expr.keyword, false);
+    }
+
+    fn visit_super(&mut self, expr: &Super) {
+        let mut this = expr.keyword.clone();
+        this.lexeme = "this".to_string();
+        self.named_variable(&this, false);
+        self.named_variable(&expr.keyword, false);
+
+        self.token = expr.method.clone();
+        let name = self.name_constant(&expr.method.lexeme);
+        self.emit_with(OpCode::GETSUPER, name);
+    }
+
+    fn visit_list(&mut self, expr: &List) {
+        for element in expr.elements.iter() {
+            self.expression(element);
+        }
+
+        self.token = expr.bracket.clone();
+        let count = self.count(expr.elements.len(), "list elements");
+        self.emit_with(OpCode::LIST, count);
+    }
+
+    fn visit_map(&mut self, expr: &Map) {
+        // Each key is checked before its value is evaluated, as the
+        // tree-walker does.
+        for (key, value) in expr.entries.iter() {
+            self.expression(key);
+            self.token = expr.brace.clone();
+            self.emit_op(OpCode::MAPKEY);
+            self.expression(value);
+        }
+
+        self.token = expr.brace.clone();
+        let count = self.count(expr.entries.len(), "map entries");
+        self.emit_with(OpCode::MAP, count);
+    }
+
+    fn visit_index(&mut self, expr: &Index) {
+        self.expression(&expr.expr);
+        self.expression(&expr.index);
+
+        self.token = expr.bracket.clone();
+        self.emit_op(OpCode::GETINDEX);
+    }
+
+    fn visit_set_index(&mut self, expr: &SetIndex) {
+        self.expression(&expr.expr);
+        self.expression(&expr.index);
+        self.expression(&expr.value);
+
+        self.token = expr.bracket.clone();
+        self.emit_op(OpCode::SETINDEX);
+    }
+
+    fn visit_interpolation(&mut self, expr: &Interpolation) {
+        for part in expr.parts.iter() {
+            self.expression(part);
+        }
+
+        let count = self.count(expr.parts.len(), "interpolated parts");
+        self.emit_with(OpCode::BUILDSTRING, count);
+    }
+
+    fn visit_lambda(&mut self, expr: &Lambda) {
+        self.function("lambda", &expr.params, &expr.body, FunctionType::FUNCTION);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::vm::chunk::{Chunk, OpCode};
+use crate::vm::value::{Function, Value};
+use std::fmt::Write;
+
+/// Lists the bytecode of `function`, followed by that of every function it
+/// contains, in the order they appear.
+pub fn disassemble(function: &Function) -> String {
+    let mut out = String::new();
+    disassemble_into(function, &mut out);
+    out
+}
+
+fn disassemble_into(function: &Function, out: &mut String) {
+    let chunk = &function.chunk;
+    writeln!(out, "== {} ==", function.name).unwrap();
+
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        let (line, next) = disassemble_instruction(chunk, offset);
+        writeln!(out, "{}", line).unwrap();
+        offset = next;
+    }
+
+    for constant in &chunk.constants {
+        if let Value::Function(f) = constant {
+            out.push('\n');
+            disassemble_into(f, out);
+        }
+    }
+}
+
+/// Formats the instruction at `offset` as one line, or several for a
+/// `CLOSURE` and its upvalues, returning it with the offset of the next
+/// instruction.
+pub fn disassemble_instruction(chunk: &Chunk, offset: usize) -> (String, usize) {
+    let mut out = format!("{:04} ", offset);
+    let line = chunk.token(offset).line;
+    if offset > 0 && chunk.token(offset - 1).line == line {
+        out.push_str("   | ");
+    } else {
+        write!(out, "{:4} ", line).unwrap();
+    }
+
+    let Some(op) = OpCode::from_byte(chunk.code[offset]) else {
+        write!(out, "Unknown opcode {}", chunk.code[offset]).unwrap();
+        return (out, offset + 1);
+    };
+    let name = format!("{:?}", op);
+
+    let next = match op {
+        OpCode::CONSTANT
+        | OpCode::GETGLOBAL
+        | OpCode::DEFINEGLOBAL
+        | OpCode::SETGLOBAL
+        | OpCode::GETPROPERTY
+        | OpCode::SETPROPERTY
+        | OpCode::GETSUPER
+        | OpCode::CLASS
+        | OpCode::METHOD => {
+            let index = chunk.read_u16(offset + 1) as usize;
+            let value = describe(&chunk.constants[index]);
+            write!(out, "{:<16} {:4} '{}'", name, index, value).unwrap();
+            offset + 3
+        }
+        OpCode::GETLOCAL
+        | OpCode::SETLOCAL
+        | OpCode::GETUPVALUE
+        | OpCode::SETUPVALUE
+        | OpCode::CALL => {
+            write!(out, "{:<16} {:4}", name, chunk.code[offset + 1]).unwrap();
+            offset + 2
+        }
+        OpCode::LIST | OpCode::MAP | OpCode::BUILDSTRING => {
+            write!(out, "{:<16} {:4}", name, chunk.read_u16(offset + 1)).unwrap();
+            offset + 3
+        }
+        OpCode::JUMP | OpCode::JUMPIFFALSE | OpCode::TRY | OpCode::TRYFINALLY => {
+            let target = offset + 3 + chunk.read_u16(offset + 1) as usize;
+            write!(out, "{:<16} {:4} -> {}", name, offset, target).unwrap();
+            offset + 3
+        }
+        OpCode::LOOP => {
+            let target = offset + 3 - chunk.read_u16(offset + 1) as usize;
+            write!(out, "{:<16} {:4} -> {}", name, offset, target).unwrap();
+            offset + 3
+        }
+        OpCode::CLOSURE => {
+            let index = chunk.read_u16(offset + 1) as usize;
+            let function = &chunk.constants[index];
+            write!(out, "{:<16} {:4} {}", name, index, describe(function)).unwrap();
+
+            let mut next = offset + 3;
+            if let Value::Function(f) = function {
+                for _ in 0..f.upvalues {
+                    let kind = if chunk.code[next] == 1 {
+                        "local"
+                    } else {
+                        "upvalue"
+                    };
+                    write!(
+                        out,
+                        "\n{:04}    |                     {} {}",
+                        next,
+                        kind,
+                        chunk.code[next + 1]
+                    )
+                    .unwrap();
+                    next += 2;
+                }
+            }
+            next
+        }
+        _ => {
+            out.push_str(&name);
+            offset + 1
+        }
+    };
+
+    (out, next)
+}
+
+/// How a value is shown in listings and traces. Functions are named, unlike
+/// when Lox code prints them.
+pub fn describe(value: &Value) -> String {
+    match value {
+        Value::Function(f) => format!("<fn {}>", f.name),
+        Value::Closure(c) => format!("<fn {}>", c.function.name),
+        Value::BoundMethod(b) => format!("<fn {}>", b.method.function.name),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,700 @@
+use crate::collections::{ListMethod, LoxMap, MapMethod, list_index, map_key};
+use crate::error::{LoxError, StackFrame};
+use crate::interpreter::{MAX_CALL_DEPTH, RuntimeError};
+use crate::scanner::Token;
+use crate::types::Callable;
+use crate::vm::chunk::OpCode;
+use crate::vm::debug::{describe, disassemble_instruction};
+use crate::vm::value::{BoundMethod, Class, Closure, Function, Instance, Native, Upvalue, Value};
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
+
+/// A function call the VM is in the middle of.
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    // Where the frame's slots start on the stack. Slot zero is the function
+    // itself, or `this` for methods.
+    base: usize,
+    // The class of the instance a method was called on, for stack traces.
+    class: Option<Rc<Class>>,
+}
+
+/// Where to resume when an exception is raised inside a `try` statement.
+struct Handler {
+    frames: usize,
+    stack: usize,
+    target: usize,
+    // Whether the handler runs a `finally` block and rethrows, rather than
+    // catching the exception.
+    finally: bool,
+}
+
+/// An error raised by the VM or a value given to `throw`, on its way to the
+/// nearest handler.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    /// What a `catch` clause receives.
+    pub value: Value,
+    message: String,
+    token: Box<Token>,
+    trace: Vec<StackFrame>,
+}
+
+/// A stack-based virtual machine that runs the bytecode made by the
+/// `Compiler`.
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<Rc<str>, Value>,
+    // Upvalues still pointing into the stack.
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    handlers: Vec<Handler>,
+    // Whether to print the stack and each instruction as it runs.
+    trace: bool,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: HashMap::from([
+                ("input".into(), Value::Native(Native::INPUT)),
+                ("println".into(), Value::Native(Native::PRINTLN)),
+                ("print".into(), Value::Native(Native::PRINT)),
+                ("gcStats".into(), Value::Native(Native::GCSTATS)),
+            ]),
+            open_upvalues: Vec::new(),
+            handlers: Vec::new(),
+            trace: false,
+        }
+    }
+
+    /// A VM that prints its stack and the next instruction to stderr before
+    /// running each instruction.
+    pub fn with_trace() -> Self {
+        Self {
+            trace: true,
+            ..Self::new()
+        }
+    }
+
+    /// Runs the top-level function of a compiled program.
+    pub fn interpret(&mut self, function: Function) -> Result<(), LoxError> {
+        let closure = Rc::new(Closure {
+            function: Rc::new(function),
+            upvalues: Vec::new(),
+        });
+        self.stack.push(Value::Closure(closure.clone()));
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: 0,
+            class: None,
+        });
+
+        loop {
+            match self.run() {
+                Ok(()) => return Ok(()),
+                Err(exception) => {
+                    if let Err(e) = self.unwind(exception) {
+                        self.stack.clear();
+                        self.frames.clear();
+                        self.open_upvalues.clear();
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Transfers control to the innermost handler, or turns the exception
+    /// into an error if there is none.
+    fn unwind(&mut self, exception: Exception) -> Result<(), LoxError> {
+        let Some(handler) = self.handlers.pop() else {
+            let error = RuntimeError::new(exception.message, *exception.token);
+            return Err(error.with_trace(exception.trace).into());
+        };
+
+        self.frames.truncate(handler.frames);
+        self.close_upvalues(handler.stack);
+        self.stack.truncate(handler.stack);
+        if handler.finally {
+            self.stack.push(Value::Exception(Rc::new(exception)));
+        } else {
+            self.stack.push(exception.value);
+        }
+        self.frame().ip = handler.target;
+
+        Ok(())
+    }
+
+    fn frame(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame();
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let frame = self.frame();
+        let value = frame.closure.function.chunk.read_u16(frame.ip);
+        frame.ip += 2;
+        value
+    }
+
+    fn read_constant(&mut self) -> Value {
+        let index = self.read_u16() as usize;
+        self.frame().closure.function.chunk.constants[index].clone()
+    }
+
+    fn read_name(&mut self) -> Rc<str> {
+        match self.read_constant() {
+            Value::Str(name) => name,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The token of the instruction being run in `frame`.
+    fn frame_token(frame: &CallFrame) -> &Token {
+        frame.closure.function.chunk.token(frame.ip - 1)
+    }
+
+    fn token(&self) -> Token {
+        Self::frame_token(self.frames.last().unwrap()).clone()
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap()
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    /// Builds the backtrace for an error on `line` in the innermost frame.
+    /// Errors outside of any function have none.
+    fn backtrace(&self, mut line: u64) -> Vec<StackFrame> {
+        let mut trace = Vec::new();
+        if self.frames.len() == 1 {
+            return trace;
+        }
+
+        for i in (1..self.frames.len()).rev() {
+            let frame = &self.frames[i];
+            let function = match &frame.class {
+                Some(class) => format!("{}.{}()", class.name, frame.closure.function.name),
+                None => format!("{}()", frame.closure.function.name),
+            };
+            trace.push(StackFrame { function, line });
+            line = Self::frame_token(&self.frames[i - 1]).line;
+        }
+        trace.push(StackFrame {
+            function: "script".to_string(),
+            line,
+        });
+
+        trace
+    }
+
+    fn exception(&self, error: RuntimeError) -> Exception {
+        let line = error.token().line;
+        Exception {
+            value: Value::Instance(Rc::new(Instance::error(error.message(), line))),
+            message: error.message().to_string(),
+            token: Box::new(error.token().clone()),
+            trace: self.backtrace(line),
+        }
+    }
+
+    fn error(&self, message: &str) -> Exception {
+        self.exception(RuntimeError::new(message.to_string(), self.token()))
+    }
+
+    fn throw(&self, value: Value) -> Exception {
+        let message = match &value {
+            Value::Instance(i) if i.error_message().is_some() => i.error_message().unwrap(),
+            _ => format!("Uncaught exception: {}", value),
+        };
+        let token = self.token();
+
+        Exception {
+            value,
+            message,
+            trace: self.backtrace(token.line),
+            token: Box::new(token),
+        }
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        for upvalue in &self.open_upvalues {
+            if let Upvalue::Open(s) = *upvalue.borrow()
+                && s == slot
+            {
+                return upvalue.clone();
+            }
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    /// Moves every variable from `from` upwards off the stack and into the
+    /// upvalues that captured it.
+    fn close_upvalues(&mut self, from: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            let Upvalue::Open(slot) = *upvalue.borrow() else {
+                return false;
+            };
+            if slot < from {
+                return true;
+            }
+
+            *upvalue.borrow_mut() = Upvalue::Closed(stack[slot].clone());
+            false
+        });
+    }
+
+    fn call(
+        &mut self,
+        closure: Rc<Closure>,
+        count: usize,
+        class: Option<Rc<Class>>,
+    ) -> Result<(), Exception> {
+        // The script's own frame isn't a call.
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return Err(self.error("Stack overflow."));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: self.stack.len() - count - 1,
+            class,
+        });
+        Ok(())
+    }
+
+    fn call_value(&mut self, count: usize) -> Result<(), Exception> {
+        let slot = self.stack.len() - count - 1;
+        let token = self.token();
+
+        match self.stack[slot].clone() {
+            Value::Closure(c) => {
+                c.check_arity(count, &token)
+                    .map_err(|e| self.exception(e))?;
+                self.call(c, count, None)
+            }
+            Value::BoundMethod(b) => {
+                b.method
+                    .check_arity(count, &token)
+                    .map_err(|e| self.exception(e))?;
+                self.stack[slot] = Value::Instance(b.receiver.clone());
+                self.call(b.method.clone(), count, Some(b.receiver.class.clone()))
+            }
+            Value::Class(c) => {
+                c.check_arity(count, &token)
+                    .map_err(|e| self.exception(e))?;
+                self.stack[slot] = Value::Instance(Rc::new(Instance::new(c.clone())));
+                match c.find_method("init") {
+                    Some(init) => self.call(init, count, Some(c)),
+                    None => Ok(()),
+                }
+            }
+            Value::Native(n) => {
+                n.check_arity(count, &token)
+                    .map_err(|e| self.exception(e))?;
+                let arguments = self.stack.split_off(slot + 1);
+                self.pop();
+                let result = n.call(&token, arguments).map_err(|e| self.exception(e))?;
+                self.push(result);
+                Ok(())
+            }
+            _ => Err(self.error("Can only call functions and classes.")),
+        }
+    }
+
+    fn binary(&mut self, op: OpCode) -> Result<(), Exception> {
+        let right = self.pop();
+        let left = self.pop();
+
+        let (l, r) = match (&left, &right) {
+            (Value::Num(l), Value::Num(r)) => (*l, *r),
+            (Value::Str(l), _) if op == OpCode::ADD => {
+                self.push(Value::Str(format!("{}{}", l, right).into()));
+                return Ok(());
+            }
+            (Value::Num(_), _) => return Err(self.error("operands must be two numbers.")),
+            _ if op == OpCode::ADD => {
+                return Err(self.error("operands must be two numbers or two strings."));
+            }
+            _ => return Err(self.error("operands must be two numbers.")),
+        };
+
+        self.push(match op {
+            OpCode::ADD => Value::Num(l + r),
+            OpCode::SUBTRACT => Value::Num(l - r),
+            OpCode::MULTIPLY => Value::Num(l * r),
+            OpCode::DIVIDE => Value::Num(l / r),
+            OpCode::GREATER => Value::Bool(l > r),
+            OpCode::GREATEREQUAL => Value::Bool(l >= r),
+            OpCode::LESS => Value::Bool(l < r),
+            OpCode::LESSEQUAL => Value::Bool(l <= r),
+            _ => unreachable!(),
+        });
+        Ok(())
+    }
+
+    fn get_property(&mut self, name: &str) -> Result<Value, Exception> {
+        let undefined = || format!("Undefined property {}", name);
+
+        match self.pop() {
+            Value::Instance(i) => {
+                if let Some(v) = i.fields.borrow().get(name) {
+                    return Ok(v.clone());
+                }
+                match i.class.find_method(name) {
+                    Some(method) => Ok(Value::BoundMethod(Rc::new(BoundMethod {
+                        receiver: i.clone(),
+                        method,
+                    }))),
+                    None => Err(self.error(&undefined())),
+                }
+            }
+            Value::List(l) => match ListMethod::from_name(name) {
+                Some(m) => Ok(Value::Native(Native::LIST(l, m))),
+                None => Err(self.error(&undefined())),
+            },
+            Value::Map(m) => match MapMethod::from_name(name) {
+                Some(method) => Ok(Value::Native(Native::MAP(m, method))),
+                None => Err(self.error(&undefined())),
+            },
+            _ => Err(self.error("Only instances have properties.")),
+        }
+    }
+
+    fn get_index(&mut self) -> Result<Value, Exception> {
+        let index = self.pop();
+        let token = self.token();
+
+        match self.pop() {
+            Value::List(l) => {
+                let l = l.borrow();
+                let i = list_index(&index, l.len(), &token).map_err(|e| self.exception(e))?;
+                Ok(l[i].clone())
+            }
+            Value::Map(m) => {
+                let key = map_key(&index, &token).map_err(|e| self.exception(e))?;
+                match m.borrow().get(&key) {
+                    Some(v) => Ok(v),
+                    None => Err(self.error(&format!("Undefined key {}.", index))),
+                }
+            }
+            _ => Err(self.error("Only lists and maps can be indexed.")),
+        }
+    }
+
+    fn set_index(&mut self) -> Result<(), Exception> {
+        let value = self.pop();
+        let index = self.pop();
+        let token = self.token();
+
+        match self.pop() {
+            Value::List(l) => {
+                let mut l = l.borrow_mut();
+                let i = list_index(&index, l.len(), &token).map_err(|e| self.exception(e))?;
+                l[i] = value.clone();
+            }
+            Value::Map(m) => {
+                let key = map_key(&index, &token).map_err(|e| self.exception(e))?;
+                m.borrow_mut().insert(key, value.clone());
+            }
+            _ => return Err(self.error("Only lists and maps can be indexed.")),
+        }
+
+        self.push(value);
+        Ok(())
+    }
+
+    fn trace_instruction(&self) {
+        let stack = self
+            .stack
+            .iter()
+            .map(|v| format!("[ {} ]", describe(v)))
+            .collect::<String>();
+        eprintln!("          {}", stack);
+
+        let frame = self.frames.last().unwrap();
+        let (instruction, _) = disassemble_instruction(&frame.closure.function.chunk, frame.ip);
+        eprintln!("{}", instruction);
+    }
+
+    /// Runs instructions until the top-level function returns.
+    fn run(&mut self) -> Result<(), Exception> {
+        loop {
+            if self.trace {
+                self.trace_instruction();
+            }
+
+            let op = match OpCode::from_byte(self.read_byte()) {
+                Some(op) => op,
+                None => unreachable!("invalid opcode"),
+            };
+
+            match op {
+                OpCode::CONSTANT => {
+                    let value = self.read_constant();
+                    self.push(value);
+                }
+                OpCode::NIL => self.push(Value::Nil),
+                OpCode::TRUE => self.push(Value::Bool(true)),
+                OpCode::FALSE => self.push(Value::Bool(false)),
+                OpCode::POP => {
+                    self.pop();
+                }
+                OpCode::GETLOCAL => {
+                    let slot = self.frame().base + self.read_byte() as usize;
+                    self.push(self.stack[slot].clone());
+                }
+                OpCode::SETLOCAL => {
+                    let slot = self.frame().base + self.read_byte() as usize;
+                    self.stack[slot] = self.peek(0).clone();
+                }
+                OpCode::GETGLOBAL => {
+                    let name = self.read_name();
+                    match self.globals.get(&name) {
+                        Some(v) => self.push(v.clone()),
+                        None => return Err(self.error("Undefined variable.")),
+                    }
+                }
+                OpCode::DEFINEGLOBAL => {
+                    let name = self.read_name();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+                OpCode::SETGLOBAL => {
+                    // Like the tree-walker, assigning to an undefined global
+                    // defines it.
+                    let name = self.read_name();
+                    self.globals.insert(name, self.peek(0).clone());
+                }
+                OpCode::GETUPVALUE => {
+                    let index = self.read_byte() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(v) => v.clone(),
+                    };
+                    self.push(value);
+                }
+                OpCode::SETUPVALUE => {
+                    let index = self.read_byte() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = self.peek(0).clone();
+                    match &mut *upvalue.borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(v) => *v = value,
+                    }
+                }
+                OpCode::GETPROPERTY => {
+                    let name = self.read_name();
+                    let value = self.get_property(&name)?;
+                    self.push(value);
+                }
+                OpCode::SETPROPERTY => {
+                    let name = self.read_name();
+                    let value = self.pop();
+                    match self.pop() {
+                        Value::Instance(i) => {
+                            i.fields
+                                .borrow_mut()
+                                .insert(name.to_string(), value.clone());
+                            self.push(value);
+                        }
+                        _ => return Err(self.error("Only instances have properties.")),
+                    }
+                }
+                OpCode::GETSUPER => {
+                    let name = self.read_name();
+                    let (Value::Class(superclass), Value::Instance(this)) =
+                        (self.pop(), self.pop())
+                    else {
+                        unreachable!()
+                    };
+                    match superclass.find_method(&name) {
+                        Some(method) => self.push(Value::BoundMethod(Rc::new(BoundMethod {
+                            receiver: this,
+                            method,
+                        }))),
+                        None => return Err(self.error(&format!("Undefined property {}", name))),
+                    }
+                }
+                OpCode::GETINDEX => {
+                    let value = self.get_index()?;
+                    self.push(value);
+                }
+                OpCode::SETINDEX => self.set_index()?,
+                OpCode::EQUAL => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.push(Value::Bool(left == right));
+                }
+                OpCode::GREATER
+                | OpCode::GREATEREQUAL
+                | OpCode::LESS
+                | OpCode::LESSEQUAL
+                | OpCode::ADD
+                | OpCode::SUBTRACT
+                | OpCode::MULTIPLY
+                | OpCode::DIVIDE => self.binary(op)?,
+                OpCode::NOT => {
+                    let value = self.pop();
+                    self.push(Value::Bool(!value.is_truthy()));
+                }
+                OpCode::NEGATE => match self.pop() {
+                    Value::Num(n) => self.push(Value::Num(-n)),
+                    _ => return Err(self.error("operands must be numbers.")),
+                },
+                OpCode::JUMP => {
+                    let distance = self.read_u16() as usize;
+                    self.frame().ip += distance;
+                }
+                OpCode::JUMPIFFALSE => {
+                    let distance = self.read_u16() as usize;
+                    if !self.peek(0).is_truthy() {
+                        self.frame().ip += distance;
+                    }
+                }
+                OpCode::LOOP => {
+                    let distance = self.read_u16() as usize;
+                    self.frame().ip -= distance;
+                }
+                OpCode::CALL => {
+                    let count = self.read_byte() as usize;
+                    self.call_value(count)?;
+                }
+                OpCode::CLOSURE => {
+                    let Value::Function(function) = self.read_constant() else {
+                        unreachable!()
+                    };
+
+                    let mut upvalues = Vec::with_capacity(function.upvalues);
+                    for _ in 0..function.upvalues {
+                        let is_local = self.read_byte() == 1;
+                        let index = self.read_byte() as usize;
+                        if is_local {
+                            let slot = self.frame().base + index;
+                            upvalues.push(self.capture_upvalue(slot));
+                        } else {
+                            upvalues.push(self.frame().closure.upvalues[index].clone());
+                        }
+                    }
+
+                    self.push(Value::Closure(Rc::new(Closure { function, upvalues })));
+                }
+                OpCode::CLOSEUPVALUE => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::RETURN => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+
+                    if self.frames.is_empty() {
+                        return Ok(());
+                    }
+                    self.push(result);
+                }
+                OpCode::CLASS => {
+                    let name = self.read_name();
+                    self.push(Value::Class(Rc::new(Class::new(&name))));
+                }
+                OpCode::INHERIT => {
+                    let Value::Class(subclass) = self.pop() else {
+                        unreachable!()
+                    };
+                    match self.peek(0) {
+                        Value::Class(superclass) => {
+                            let methods = superclass.methods.borrow().clone();
+                            subclass.methods.borrow_mut().extend(methods);
+                        }
+                        _ => return Err(self.error("Superclass must be a class.")),
+                    }
+                }
+                OpCode::METHOD => {
+                    let name = self.read_name();
+                    let (Value::Closure(method), Value::Class(class)) = (self.pop(), self.peek(0))
+                    else {
+                        unreachable!()
+                    };
+                    class.methods.borrow_mut().insert(name.to_string(), method);
+                }
+                OpCode::LIST => {
+                    let count = self.read_u16() as usize;
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    self.push(Value::List(Rc::new(RefCell::new(elements))));
+                }
+                OpCode::MAP => {
+                    let count = self.read_u16() as usize;
+                    let entries = self.stack.split_off(self.stack.len() - count * 2);
+
+                    let mut map = LoxMap::new();
+                    for entry in entries.chunks(2) {
+                        map.insert(entry[0].clone(), entry[1].clone());
+                    }
+                    self.push(Value::Map(Rc::new(RefCell::new(map))));
+                }
+                OpCode::BUILDSTRING => {
+                    let count = self.read_u16() as usize;
+                    let parts = self.stack.split_off(self.stack.len() - count);
+                    let string = parts.iter().map(|p| p.to_string()).collect::<String>();
+                    self.push(Value::Str(string.into()));
+                }
+                OpCode::MAPKEY => {
+                    map_key(self.peek(0), &self.token()).map_err(|e| self.exception(e))?;
+                }
+                OpCode::THROW => {
+                    let value = self.pop();
+                    return Err(self.throw(value));
+                }
+                OpCode::TRY | OpCode::TRYFINALLY => {
+                    let target = self.read_u16() as usize + self.frame().ip;
+                    self.handlers.push(Handler {
+                        frames: self.frames.len(),
+                        stack: self.stack.len(),
+                        target,
+                        finally: op == OpCode::TRYFINALLY,
+                    });
+                }
+                OpCode::POPHANDLER => {
+                    self.handlers.pop();
+                }
+                OpCode::RETHROW => {
+                    let Value::Exception(exception) = self.pop() else {
+                        unreachable!()
+                    };
+                    return Err(Rc::unwrap_or_clone(exception));
+                }
+            }
+        }
+    }
+}
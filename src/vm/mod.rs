@@ -0,0 +1,12 @@
+//! The bytecode backend. The `Compiler` turns a resolved program into chunks
+//! of bytecode, which the stack-based `Vm` then runs, in the style of clox.
+
+pub mod chunk;
+pub mod compiler;
+pub mod debug;
+pub mod machine;
+pub mod value;
+
+pub use compiler::Compiler;
+pub use debug::disassemble;
+pub use machine::Vm;
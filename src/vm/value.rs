@@ -0,0 +1,361 @@
+use crate::collections::{
+    Collection, Element, ListMethod, LoxMap, MapMethod, eq_collections, fmt_collection,
+};
+use crate::interpreter::RuntimeError;
+use crate::scanner::Token;
+use crate::types::{Callable, Object};
+use crate::vm::chunk::Chunk;
+use crate::vm::machine::Exception;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::{cell::RefCell, rc::Rc};
+
+/// A compiled function. It only exists at runtime wrapped in a `Closure`.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub upvalues: usize,
+    pub chunk: Chunk,
+}
+
+impl Function {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            arity: 0,
+            upvalues: 0,
+            chunk: Chunk::new(),
+        }
+    }
+}
+
+/// A variable captured by a closure. It points into the stack while the
+/// variable's scope is still running, and holds the value once it has ended.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl Callable for Closure {
+    fn arity(&self) -> RangeInclusive<usize> {
+        self.function.arity..=self.function.arity
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub methods: RefCell<HashMap<String, Rc<Closure>>>,
+}
+
+impl Class {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            methods: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Rc<Closure>> {
+        self.methods.borrow().get(name).cloned()
+    }
+}
+
+impl Callable for Class {
+    /// Constructing an instance takes the arguments of `init`, if any.
+    fn arity(&self) -> RangeInclusive<usize> {
+        match self.find_method("init") {
+            Some(init) => init.arity(),
+            None => 0..=0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: RefCell<HashMap<String, Value>>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// An `Error` object, which is what `catch` receives for runtime errors
+    /// raised by the VM itself.
+    pub fn error(message: &str, line: u64) -> Self {
+        let instance = Self::new(Rc::new(Class::new("Error")));
+        instance.fields.borrow_mut().extend([
+            ("message".to_string(), Value::Str(message.into())),
+            ("line".to_string(), Value::Num(line as f64)),
+        ]);
+
+        instance
+    }
+
+    /// The message of an `Error` object, or `None` for other instances.
+    pub fn error_message(&self) -> Option<String> {
+        if self.class.name != "Error" {
+            return None;
+        }
+
+        match self.fields.borrow().get("message") {
+            Some(Value::Str(message)) => Some(message.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// A method looked up on an instance, remembering the instance as `this`.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Rc<Instance>,
+    pub method: Rc<Closure>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Native {
+    INPUT,
+    PRINTLN,
+    PRINT,
+    GCSTATS,
+    LIST(Rc<RefCell<Vec<Value>>>, ListMethod),
+    MAP(Rc<RefCell<LoxMap<Value>>>, MapMethod),
+}
+
+impl Callable for Native {
+    fn arity(&self) -> RangeInclusive<usize> {
+        match self {
+            // input() takes an optional prompt.
+            Self::INPUT => 0..=1,
+            Self::PRINTLN | Self::PRINT => 0..=usize::MAX,
+            Self::GCSTATS => 0..=0,
+            Self::LIST(_, method) => method.arity()..=method.arity(),
+            Self::MAP(_, method) => method.arity()..=method.arity(),
+        }
+    }
+}
+
+impl Native {
+    pub fn call(&self, name: &Token, params: Vec<Value>) -> Result<Value, RuntimeError> {
+        match self {
+            Self::INPUT => {
+                if params.len() == 1 {
+                    print!("{}", params[0]);
+                    if let Err(e) = io::stdout().flush() {
+                        return Err(RuntimeError::new(e.to_string(), name.clone()));
+                    }
+                }
+
+                let mut input = String::new();
+                match io::stdin().read_line(&mut input) {
+                    Ok(_) => Ok(Value::Str(input.trim_end().into())),
+                    Err(e) => Err(RuntimeError::new(e.to_string(), name.clone())),
+                }
+            }
+
+            Self::PRINTLN => {
+                println!("{}", join(&params));
+                Ok(Value::Nil)
+            }
+
+            Self::PRINT => {
+                print!("{}", join(&params));
+                if let Err(e) = io::stdout().flush() {
+                    return Err(RuntimeError::new(e.to_string(), name.clone()));
+                }
+                Ok(Value::Nil)
+            }
+
+            // The VM has no collector, so there is nothing to count. The keys
+            // match the tree-walker's so scripts run on either backend.
+            Self::GCSTATS => {
+                let mut map = LoxMap::new();
+                for key in ["collections", "allocated", "freed", "live"] {
+                    map.insert(Value::Str(key.into()), Value::Num(0.0));
+                }
+                Ok(Value::Map(Rc::new(RefCell::new(map))))
+            }
+
+            Self::LIST(list, method) => method.call(&mut list.borrow_mut(), &params, name),
+            Self::MAP(map, method) => method.call(&mut map.borrow_mut(), &params, name),
+        }
+    }
+}
+
+fn join(params: &[Value]) -> String {
+    params
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A value on the VM's stack. Unlike the tree-walker's `Object`, functions,
+/// classes and instances are shared by reference.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Num(f64),
+    Str(Rc<str>),
+    Bool(bool),
+    Function(Rc<Function>),
+    Closure(Rc<Closure>),
+    BoundMethod(Rc<BoundMethod>),
+    Native(Native),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<LoxMap<Value>>>),
+    /// An exception on its way through a `finally` block. Never visible to
+    /// Lox code.
+    Exception(Rc<Exception>),
+    Nil,
+}
+
+impl Value {
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Bool(b) => *b,
+            Self::Nil => false,
+            _ => true,
+        }
+    }
+}
+
+impl Element for Value {
+    fn nil() -> Self {
+        Self::Nil
+    }
+
+    fn boolean(b: bool) -> Self {
+        Self::Bool(b)
+    }
+
+    fn number(n: f64) -> Self {
+        Self::Num(n)
+    }
+
+    fn list(elements: Vec<Self>) -> Self {
+        Self::List(Rc::new(RefCell::new(elements)))
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Num(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn is_primitive(&self) -> bool {
+        matches!(
+            self,
+            Self::Num(_) | Self::Str(_) | Self::Bool(_) | Self::Nil
+        )
+    }
+
+    fn collection(&self) -> Option<Collection<'_, Self>> {
+        match self {
+            Self::List(l) => Some(Collection::List(l)),
+            Self::Map(m) => Some(Collection::Map(m)),
+            _ => None,
+        }
+    }
+}
+
+impl From<&Object> for Value {
+    /// Converts a literal from the syntax tree.
+    fn from(object: &Object) -> Self {
+        match object {
+            Object::Num(n) => Self::Num(*n),
+            Object::Str(s) => Self::Str(s.as_str().into()),
+            Object::Bool(b) => Self::Bool(*b),
+            _ => Self::Nil,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Num(l), Self::Num(r)) => l == r,
+            (Self::Str(l), Self::Str(r)) => l == r,
+            (Self::Bool(l), Self::Bool(r)) => l == r,
+            (Self::Nil, Self::Nil) => true,
+            (Self::List(l), Self::List(r)) => {
+                eq_collections(Collection::List(l), Collection::List(r))
+            }
+            (Self::Map(l), Self::Map(r)) => eq_collections(Collection::Map(l), Collection::Map(r)),
+            (Self::Function(l), Self::Function(r)) => Rc::ptr_eq(l, r),
+            (Self::Closure(l), Self::Closure(r)) => Rc::ptr_eq(l, r),
+            (Self::BoundMethod(l), Self::BoundMethod(r)) => {
+                Rc::ptr_eq(&l.receiver, &r.receiver) && Rc::ptr_eq(&l.method, &r.method)
+            }
+            (Self::Native(l), Self::Native(r)) => match (l, r) {
+                (Native::INPUT, Native::INPUT)
+                | (Native::PRINTLN, Native::PRINTLN)
+                | (Native::PRINT, Native::PRINT)
+                | (Native::GCSTATS, Native::GCSTATS) => true,
+                (Native::LIST(l, a), Native::LIST(r, b)) => Rc::ptr_eq(l, r) && a == b,
+                (Native::MAP(l, a), Native::MAP(r, b)) => Rc::ptr_eq(l, r) && a == b,
+                _ => false,
+            },
+            (Self::Class(l), Self::Class(r)) => Rc::ptr_eq(l, r),
+            (Self::Instance(l), Self::Instance(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+// Only numbers, strings, booleans and nil are ever hashed (see `map_key`), and
+// NaN is rejected there, so equality is reflexive for every hashed value.
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            // -0.0 == 0.0, so both must hash the same.
+            Self::Num(n) if *n == 0.0 => 0.0f64.to_bits().hash(state),
+            Self::Num(n) => n.to_bits().hash(state),
+            Self::Str(s) => s.hash(state),
+            Self::Bool(b) => b.hash(state),
+            _ => (),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Formats values exactly like the tree-walker's `Object`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Num(n) => write!(f, "{}", n),
+            Self::Str(s) => write!(f, "{}", s),
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Function(_) | Self::Closure(_) | Self::BoundMethod(_) => {
+                write!(f, "<user defined> fn")
+            }
+            Self::Native(_) => write!(f, "native fn"),
+            Self::Class(c) => write!(f, "{}", c.name),
+            Self::Instance(i) => write!(f, "{} instance", i.class.name),
+            Self::List(l) => fmt_collection(Collection::List(l), f),
+            Self::Map(m) => fmt_collection(Collection::Map(m), f),
+            Self::Exception(e) => write!(f, "{}", e.value),
+            Self::Nil => write!(f, "nil"),
+        }
+    }
+}
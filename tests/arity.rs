@@ -0,0 +1,48 @@
+mod common;
+
+use common::{assert_runtime_error, run};
+
+#[test]
+fn functions_check_their_arguments() {
+    assert_runtime_error("fun f(a) {}\nf(1, 2);", "Expected 1 arguments but got 2.");
+    assert_runtime_error("var f = (a) => a;\nf();", "Expected 1 arguments but got 0.");
+}
+
+#[test]
+fn methods_check_their_arguments() {
+    assert_runtime_error(
+        "class A { m(a, b) {} }\nA().m(1);",
+        "Expected 2 arguments but got 1.",
+    );
+    assert_runtime_error(
+        "class A { init(x) {} }\nvar a = A(1);\na.init();",
+        "Expected 1 arguments but got 0.",
+    );
+}
+
+#[test]
+fn constructors_take_the_arguments_of_init() {
+    assert_runtime_error(
+        "class A { init(x) {} }\nA();",
+        "Expected 1 arguments but got 0.",
+    );
+    assert_runtime_error("class A {}\nA(1);", "Expected 0 arguments but got 1.");
+    assert_runtime_error(
+        "class B { init(x) {} }\nclass C < B {}\nC();",
+        "Expected 1 arguments but got 0.",
+    );
+}
+
+#[test]
+fn natives_check_their_arguments() {
+    assert_runtime_error("input(1, 2);", "Expected 0 to 1 arguments but got 2.");
+    assert_runtime_error("[].push(1, 2);", "Expected 1 arguments but got 2.");
+    assert_runtime_error("var m = {};\nm.has();", "Expected 1 arguments but got 0.");
+}
+
+#[test]
+fn error_points_at_the_call() {
+    let output = run("fun f(a) {}\nf(1, 2);");
+
+    assert!(output.stderr.contains(":2:7"), "{}", output.stderr);
+}
@@ -0,0 +1,112 @@
+// Not every test binary uses every helper.
+#![allow(dead_code)]
+
+use rlox::error::LoxError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_SCRIPT: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: Option<i32>,
+}
+
+/// Writes `source` to a temporary script and runs it through the `rlox` binary.
+pub fn run(source: &str) -> Output {
+    run_with(source, &[])
+}
+
+/// Like `run`, passing `args` before the script.
+pub fn run_with(source: &str, args: &[&str]) -> Output {
+    let path = script_path();
+    std::fs::write(&path, source).unwrap();
+
+    let output = run_file(&path, args);
+    std::fs::remove_file(&path).unwrap();
+
+    output
+}
+
+/// Runs `source` on both backends, checking that each fails with the runtime
+/// error `message`.
+pub fn assert_runtime_error(source: &str, message: &str) {
+    for args in [&[][..], &["--vm"]] {
+        let output = run_with(source, args);
+        assert!(
+            output
+                .stderr
+                .contains(&format!("runtime error: {}", message)),
+            "{:?}: {}",
+            args,
+            output.stderr
+        );
+    }
+}
+
+/// Runs `source`, checking that scanning fails with the error `message`.
+pub fn assert_scan_error(source: &str, message: &str) {
+    let output = run(source);
+
+    assert!(
+        output.stderr.contains(&format!("scan error: {}", message)),
+        "{}: {}",
+        source,
+        output.stderr
+    );
+}
+
+/// The line and message of each error.
+pub fn summary(errors: &[LoxError]) -> Vec<(u64, &str)> {
+    errors
+        .iter()
+        .map(|e| (e.location().line, e.message()))
+        .collect()
+}
+
+/// Runs the script at `path` through the `rlox` binary, passing `args`
+/// before it.
+pub fn run_file(path: &Path, args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap();
+
+    Output {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        status: output.status.code(),
+    }
+}
+
+/// Runs the `rlox` binary without a script, typing `input` into its REPL.
+pub fn run_repl(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    Output {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        status: output.status.code(),
+    }
+}
+
+fn script_path() -> PathBuf {
+    let id = NEXT_SCRIPT.fetch_add(1, Ordering::SeqCst);
+    std::env::temp_dir().join(format!("rlox-test-{}-{}.lox", std::process::id(), id))
+}
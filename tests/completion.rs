@@ -0,0 +1,120 @@
+//! The REPL completes keywords and the globals of the session, which come
+//! from `KEYWORDS` and `Interpreter::global_names`. `complete` picks the ones
+//! that match the word before the cursor.
+
+use rlox::completion::complete;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::{KEYWORDS, Scanner};
+
+fn globals_after(source: &str) -> Vec<String> {
+    let tokens = Scanner::new(source.chars().peekable())
+        .scan_tokens()
+        .unwrap();
+    let (stmts, errors) = Parser::new(tokens).parse();
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(Vec::new(), &mut interpreter);
+    resolver.resolve_stmts(&stmts).unwrap();
+    interpreter.interpret(stmts).unwrap();
+
+    let mut names = interpreter.global_names();
+    names.sort();
+    names
+}
+
+#[test]
+fn globals_include_natives() {
+    let names = globals_after("");
+
+    for native in ["input", "print", "println"] {
+        assert!(names.iter().any(|n| n == native), "{:?}", names);
+    }
+}
+
+#[test]
+fn globals_include_declarations_but_not_locals() {
+    let names = globals_after(
+        "var counter = 0;
+        fun tick() { var step = 1; counter = counter + step; }
+        class Point {}
+        { var hidden = 1; }",
+    );
+
+    for name in ["Point", "counter", "tick"] {
+        assert!(names.iter().any(|n| n == name), "{:?}", names);
+    }
+    for name in ["hidden", "step"] {
+        assert!(!names.iter().any(|n| n == name), "{:?}", names);
+    }
+}
+
+#[test]
+fn keywords_include_every_statement_keyword() {
+    for keyword in [
+        "break", "catch", "class", "continue", "finally", "for", "fun", "return", "super", "this",
+        "throw", "try", "var", "while",
+    ] {
+        assert!(KEYWORDS.contains_key(keyword), "{}", keyword);
+    }
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn completes_keywords_and_globals_with_the_prefix() {
+    let globals = names(&["counter", "class_count", "println"]);
+
+    assert_eq!(
+        complete("var x = cl", 10, &globals),
+        (8, names(&["class", "class_count"]))
+    );
+    assert_eq!(
+        complete("co", 2, &globals),
+        (0, names(&["continue", "counter"]))
+    );
+}
+
+#[test]
+fn duplicate_candidates_are_listed_once() {
+    let globals = names(&["print", "print"]);
+
+    assert_eq!(complete("pri", 3, &globals), (0, names(&["print"])));
+}
+
+#[test]
+fn empty_prefix_completes_everything() {
+    let (start, candidates) = complete("x + ", 4, &names(&["x"]));
+
+    assert_eq!(start, 4);
+    assert!(candidates.iter().any(|c| c == "x"), "{:?}", candidates);
+    assert!(candidates.iter().any(|c| c == "while"), "{:?}", candidates);
+}
+
+#[test]
+fn prefix_ends_at_the_cursor() {
+    let globals = names(&["println", "print"]);
+
+    // The cursor is after `pri` in `println(1)`.
+    assert_eq!(
+        complete("println(1)", 3, &globals),
+        (0, names(&["print", "println"]))
+    );
+    assert_eq!(complete("f(wh, 1)", 4, &globals), (2, names(&["while"])));
+}
+
+#[test]
+fn prefix_after_a_multibyte_character() {
+    let globals = names(&["println"]);
+    let line = "\"→pri";
+
+    assert_eq!(
+        complete(line, line.len(), &globals),
+        (4, names(&["println"]))
+    );
+    assert_eq!(complete("→", 3, &globals).0, 3);
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::run_file;
+use std::path::{Path, PathBuf};
+
+/// The scripts in `tests/conformance`. Each one states what it prints with
+/// `// expect: ` comments, and how it fails, if it does, with a final
+/// `// expect runtime error: ` comment.
+fn scripts() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let mut scripts = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "lox"))
+        .collect::<Vec<_>>();
+    scripts.sort();
+
+    scripts
+}
+
+fn expected_output(source: &str) -> String {
+    source
+        .lines()
+        .filter_map(|line| line.split_once("// expect: "))
+        .map(|(_, expected)| format!("{}\n", expected))
+        .collect()
+}
+
+fn expected_error(source: &str) -> Option<&str> {
+    source
+        .lines()
+        .find_map(|line| line.split_once("// expect runtime error: "))
+        .map(|(_, message)| message)
+}
+
+#[test]
+fn tree_walker_matches_expectations() {
+    for script in scripts() {
+        let source = std::fs::read_to_string(&script).unwrap();
+        let output = run_file(&script, &[]);
+
+        assert_eq!(
+            output.stdout,
+            expected_output(&source),
+            "{}",
+            script.display()
+        );
+        match expected_error(&source) {
+            Some(message) => assert!(
+                output
+                    .stderr
+                    .contains(&format!("runtime error: {}", message)),
+                "{}: {}",
+                script.display(),
+                output.stderr
+            ),
+            None => assert_eq!(output.stderr, "", "{}", script.display()),
+        }
+    }
+}
+
+#[test]
+fn vm_behaves_like_tree_walker() {
+    for script in scripts() {
+        let tree_walker = run_file(&script, &[]);
+        let vm = run_file(&script, &["--vm"]);
+
+        assert_eq!(vm.stdout, tree_walker.stdout, "{}", script.display());
+        assert_eq!(vm.stderr, tree_walker.stderr, "{}", script.display());
+        assert_eq!(vm.status, tree_walker.status, "{}", script.display());
+    }
+}
+
+#[test]
+fn tree_walker_survives_gc_stress() {
+    for script in scripts() {
+        let normal = run_file(&script, &[]);
+        let stressed = run_file(&script, &["--gc-stress"]);
+
+        assert_eq!(stressed.stdout, normal.stdout, "{}", script.display());
+        assert_eq!(stressed.stderr, normal.stderr, "{}", script.display());
+        assert_eq!(stressed.status, normal.status, "{}", script.display());
+    }
+}
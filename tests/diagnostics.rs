@@ -0,0 +1,98 @@
+use rlox::error::{Location, LoxError};
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::{Scanner, Span};
+
+/// Renders the first error `source` fails with, without colour.
+fn render(source: &str) -> String {
+    let tokens = Scanner::new(source.chars().peekable())
+        .scan_tokens()
+        .unwrap();
+    let (stmts, errors) = Parser::new(tokens).parse();
+
+    let error = match errors.into_iter().next() {
+        Some(error) => error,
+        None => {
+            let mut interpreter = Interpreter::new();
+            let mut resolver = Resolver::new(Vec::new(), &mut interpreter);
+            match resolver.resolve_stmts(&stmts) {
+                Err(errors) => errors.into_iter().next().unwrap(),
+                Ok(()) => interpreter.interpret(stmts).unwrap_err(),
+            }
+        }
+    };
+
+    error.render(source, "test.lox", false)
+}
+
+#[test]
+fn carets_underline_the_offending_token() {
+    assert_eq!(
+        render("var x = 1;\nprintln(missingName);"),
+        "\
+runtime error: Undefined variable.
+ --> test.lox:2:9
+  |
+2 | println(missingName);
+  |         ^^^^^^^^^^^"
+    );
+}
+
+#[test]
+fn labels_point_at_related_code() {
+    assert_eq!(
+        render("fun f() {\n  var a = 1;\n\n  var a = 2;\n}"),
+        "\
+resolve error: Already a variable with this name in this scope.
+ --> test.lox:4:7
+  |
+2 |   var a = 1;
+  |       - variable declared here
+...
+4 |   var a = 2;
+  |       ^"
+    );
+}
+
+#[test]
+fn tabs_are_expanded_so_carets_line_up() {
+    assert_eq!(
+        render("\tvar y = -\"s\";"),
+        "\
+runtime error: operands must be numbers.
+ --> test.lox:1:10
+  |
+1 |     var y = -\"s\";
+  |             ^"
+    );
+}
+
+#[test]
+fn gutter_fits_the_widest_line_number() {
+    let source = format!("{}var = 1;", "\n".repeat(9));
+
+    assert_eq!(
+        render(&source),
+        "\
+parse error: Expect variable name.
+  --> test.lox:10:5
+   |
+10 | var = 1;
+   |     ^"
+    );
+}
+
+#[test]
+fn colour_is_only_added_when_asked_for() {
+    let location = Location {
+        line: 1,
+        column: 1,
+        span: Span::new(0, 1),
+        lexeme: None,
+    };
+    let error = LoxError::scan(location, "Unexpected character.");
+
+    assert!(error.render("@", "test.lox", true).contains("\x1b[1;31m"));
+    assert!(!error.render("@", "test.lox", false).contains('\x1b'));
+}
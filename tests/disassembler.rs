@@ -0,0 +1,44 @@
+mod common;
+
+use std::path::PathBuf;
+
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rlox-{}-{}.lox", name, std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn disasm_lists_every_function() {
+    let path = script(
+        "disasm",
+        "fun add(a, b) {\n  return a + b;\n}\nprintln(add(1, 2));\n",
+    );
+    let output = common::run_file(&path, &["disasm"]);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status, Some(0));
+    assert!(output.stdout.starts_with("== script ==\n"));
+    assert!(output.stdout.contains("CLOSURE             1 <fn add>"));
+    assert!(output.stdout.contains("CALL                2"));
+    assert!(output.stdout.contains("\n== add ==\n"));
+    assert!(output.stdout.contains("0000    2 GETLOCAL            1"));
+    assert!(
+        !output.stdout.lines().any(|l| l == "3"),
+        "disasm ran the script"
+    );
+}
+
+#[test]
+fn trace_prints_the_stack_before_each_instruction() {
+    let path = script("trace", "println(1 + 2);\n");
+    let output = common::run_file(&path, &["--trace"]);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.stdout, "3\n");
+    assert!(
+        output
+            .stderr
+            .contains("[ <fn script> ][ native fn ][ 1 ][ 2 ]\n0009    | ADD")
+    );
+}
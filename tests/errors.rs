@@ -0,0 +1,82 @@
+use rlox::error::LoxError;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+
+/// Runs `source` through every stage, returning the errors of the first one
+/// that fails.
+fn errors(source: &str) -> Vec<LoxError> {
+    let tokens = match Scanner::new(source.chars().peekable()).scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(errors) => return errors,
+    };
+    let (stmts, errors) = Parser::new(tokens).parse();
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(Vec::new(), &mut interpreter);
+    if let Err(errors) = resolver.resolve_stmts(&stmts) {
+        return errors;
+    }
+
+    match interpreter.interpret(stmts) {
+        Ok(()) => Vec::new(),
+        Err(error) => vec![error],
+    }
+}
+
+#[test]
+fn each_stage_reports_its_own_kind() {
+    let cases = [
+        ("var s = \"open;", "Scan"),
+        ("var = 1;", "Parse"),
+        ("return 1;", "Resolve"),
+        ("println(-\"a\");", "Runtime"),
+    ];
+
+    for (source, kind) in cases {
+        let errors = errors(source);
+        assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+        assert_eq!(errors[0].kind(), kind, "{}", source);
+    }
+}
+
+#[test]
+fn errors_match_on_their_variant() {
+    let errors = errors("var = 1;");
+
+    assert!(matches!(&errors[0], LoxError::Parse(d) if d.message == "Expect variable name."));
+}
+
+#[test]
+fn display_shows_location_kind_and_lexeme() {
+    let errors = errors("var x = 1;\nvar = 2;");
+
+    assert_eq!(
+        errors[0].to_string(),
+        "[line 2:5] Parse error at '=': Expect variable name."
+    );
+}
+
+#[test]
+fn errors_at_end_of_input_say_end() {
+    let errors = errors("var x = 1");
+
+    assert_eq!(errors[0].location().lexeme.as_deref(), Some("end"));
+}
+
+#[test]
+fn runtime_errors_keep_their_trace() {
+    let errors = errors("fun f() {\n  return nil + 1;\n}\nf();");
+
+    assert_eq!(errors[0].kind(), "Runtime");
+    assert_eq!(
+        errors[0].message(),
+        "operands must be two numbers or two strings."
+    );
+    let trace: Vec<String> = errors[0].trace().iter().map(|f| f.to_string()).collect();
+    assert_eq!(trace, ["[line 2] in f()", "[line 4] in script"]);
+}
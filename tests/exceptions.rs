@@ -0,0 +1,50 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn try_needs_catch_or_finally() {
+    let output = run("try {}\nprintln(1);");
+
+    assert!(
+        output
+            .stderr
+            .contains("Expect 'catch' or 'finally' after try block."),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.stdout, "");
+}
+
+#[test]
+fn catch_needs_a_variable() {
+    let output = run("try {} catch {}");
+
+    assert!(
+        output.stderr.contains("Expect '(' after 'catch'."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn throw_needs_a_value() {
+    let output = run("throw;");
+
+    assert!(
+        output.stderr.contains("Expect expression"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn catch_variable_is_scoped_to_its_block() {
+    let output = run("try {} catch (e) {}\nprintln(e);");
+
+    assert!(
+        output.stderr.contains("Undefined variable."),
+        "{}",
+        output.stderr
+    );
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rlox::statements::Stmt;
+use rlox::types::Object;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+#[test]
+fn cycles_through_environments_and_instances_are_collected() {
+    let output = common::run(
+        r#"
+class Node {
+  init() {
+    this.self = this;
+    this.method = this.get;
+  }
+  get() { return this; }
+}
+
+fun countdown(n) {
+  if (n > 0) return countdown(n - 1);
+  return 0;
+}
+
+for (var i = 0; i < 5000; i = i + 1) {
+  Node();
+  countdown(3);
+}
+
+var stats = gcStats();
+println(stats["collections"] > 0, stats["freed"] > 5000, stats["live"] < 2048);
+"#,
+    );
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "true true true\n");
+}
+
+#[test]
+fn values_in_flight_survive_collections() {
+    let path = std::env::temp_dir().join(format!("rlox-gc-{}.lox", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+class Pair {
+  init(a, b) { this.a = a; this.b = b; }
+  sum() { return this.a.n + this.b.n; }
+}
+class Num { init(n) { this.n = n; } }
+
+fun make(n) { return Num(n); }
+var pairs = [];
+for (var i = 0; i < 3; i = i + 1) {
+  pairs.push(Pair(make(i), make(i * 10)));
+}
+
+fun thrower() {
+  try { throw Num(7); } finally { var junk = Num(0); }
+}
+try { thrower(); } catch (e) { println(e.n); }
+
+println(pairs[2].sum(), {"k": Pair(Num(1), Num(2))}["k"].sum());
+"#,
+    )
+    .unwrap();
+
+    let output = common::run_file(&path, &["--gc-stress"]);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "7\n22 3\n");
+}
+
+#[test]
+fn cycles_through_lists_and_instances_are_collected() {
+    let output = common::run(
+        r#"
+class Holder {}
+
+for (var i = 0; i < 5000; i = i + 1) {
+  var holder = Holder();
+  holder.items = [holder, {"holder": holder}];
+}
+
+var stats = gcStats();
+println(stats["freed"] > 4000, stats["live"] < 2048);
+"#,
+    );
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "true true\n");
+}
+
+#[test]
+fn gc_stats_are_zero_on_the_vm() {
+    let output = common::run_with(
+        r#"
+fun countdown(n) {
+  if (n > 0) return countdown(n - 1);
+  return 0;
+}
+countdown(3);
+println(gcStats());
+"#,
+        &["--vm"],
+    );
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(
+        output.stdout,
+        "{collections: 0, allocated: 0, freed: 0, live: 0}\n"
+    );
+}
+
+/// Runs `source`, which defines a global list `xs`, and returns a weak
+/// reference to that list that outlives the interpreter.
+fn list_after(source: &str) -> Weak<RefCell<Vec<Object>>> {
+    let parse = |source: &str| {
+        let tokens = Scanner::new(source.chars().peekable())
+            .scan_tokens()
+            .unwrap();
+        let (stmts, errors) = Parser::new(tokens).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+        stmts
+    };
+
+    let mut interpreter = Interpreter::new();
+    let stmts = parse(source);
+    Resolver::new(Vec::new(), &mut interpreter)
+        .resolve_stmts(&stmts)
+        .unwrap();
+    interpreter.interpret(stmts).unwrap();
+
+    let stmts = parse("xs;");
+    let [Stmt::ExprStmt(expr)] = stmts.as_slice() else {
+        unreachable!()
+    };
+    match interpreter.interpret_expr(expr) {
+        Ok(Object::List(xs)) => Rc::downgrade(&xs),
+        _ => panic!("xs isn't a list"),
+    }
+}
+
+#[test]
+fn lists_and_maps_that_contain_themselves_are_never_freed() {
+    // They are reference counted rather than kept on the heap, so a cycle
+    // made only of lists and maps keeps itself alive.
+    assert!(list_after("var xs = []; xs.push(xs);").upgrade().is_some());
+    assert!(
+        list_after(r#"var xs = [{}]; xs[0]["xs"] = xs;"#)
+            .upgrade()
+            .is_some()
+    );
+
+    assert!(list_after("var xs = [[1], {}];").upgrade().is_none());
+    assert!(
+        list_after("class A {} var xs = [A()]; xs[0].xs = xs;")
+            .upgrade()
+            .is_none()
+    );
+}
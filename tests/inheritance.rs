@@ -0,0 +1,62 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn class_cannot_inherit_from_itself() {
+    let output = run("class A < A {}");
+
+    assert!(
+        output.stderr.contains("A class can't inherit from itself."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn super_outside_class_is_an_error() {
+    let output = run("super.method();");
+
+    assert!(
+        output
+            .stderr
+            .contains("Can't use 'super' outside of a class."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn super_without_superclass_is_an_error() {
+    let output = run(r#"
+        class A {
+          method() {
+            super.method();
+          }
+        }
+    "#);
+
+    assert!(
+        output
+            .stderr
+            .contains("Can't use 'super' in a class with no superclass."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn inherited_init_runs_for_subclass() {
+    let output = run(r#"
+        class Base {
+          init(value) {
+            this.value = value;
+          }
+        }
+        class Derived < Base {}
+        println(Derived(3).value);
+    "#);
+
+    assert_eq!(output.stdout, "3\n");
+    assert_eq!(output.stderr, "");
+}
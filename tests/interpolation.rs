@@ -0,0 +1,44 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn unterminated_interpolation_is_an_error() {
+    let output = run("\"${\"open}\";");
+
+    assert!(
+        output
+            .stderr
+            .contains("scan error: Unterminated string interpolation."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn interpolated_expressions_must_be_complete() {
+    for source in ["\"${}\";", "\"${1 +}\";"] {
+        let output = run(source);
+
+        assert!(
+            output
+                .stderr
+                .contains("Expect '}' after interpolated expression."),
+            "{}: {}",
+            source,
+            output.stderr
+        );
+    }
+}
+
+#[test]
+fn errors_point_inside_the_string() {
+    let output = run("var s = \"a ${missing}\";");
+
+    assert!(
+        output.stderr.contains("Undefined variable."),
+        "{}",
+        output.stderr
+    );
+    assert!(output.stderr.contains(":1:14"), "{}", output.stderr);
+}
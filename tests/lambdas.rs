@@ -0,0 +1,39 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn arrow_needs_a_body() {
+    let output = run("var f = (x) => ;");
+
+    assert!(
+        output.stderr.contains("Expect expression"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn arrow_parameters_must_be_names() {
+    let output = run("var f = (a, 1) => a;");
+
+    assert!(output.stderr.contains("parse error"), "{}", output.stderr);
+}
+
+#[test]
+fn lambdas_are_functions_to_the_resolver() {
+    let output = run("var f = fun () { return 1; };\nvar g = () => this;");
+
+    assert!(
+        !output.stderr.contains("Can't return from top-level code."),
+        "{}",
+        output.stderr
+    );
+    assert!(
+        output
+            .stderr
+            .contains("Can't use `this` keyword from top-level code."),
+        "{}",
+        output.stderr
+    );
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::assert_runtime_error;
+
+#[test]
+fn popping_an_empty_list_is_an_error() {
+    assert_runtime_error("[].pop();", "Can't pop from an empty list.");
+}
+
+#[test]
+fn indexes_must_be_integers() {
+    assert_runtime_error("[1, 2][0.5];", "List index must be an integer.");
+    assert_runtime_error("[1, 2][\"0\"];", "List index must be an integer.");
+}
+
+#[test]
+fn indexes_are_bounds_checked() {
+    assert_runtime_error("[1][-1];", "Index -1 out of bounds for length 1.");
+    assert_runtime_error(
+        "var xs = [1];\nxs[1] = 2;",
+        "Index 1 out of bounds for length 1.",
+    );
+    assert_runtime_error("[1].remove(1);", "Index 1 out of bounds for length 1.");
+    assert_runtime_error("[1].insert(2, 0);", "Index 2 out of bounds for length 2.");
+}
+
+#[test]
+fn only_lists_and_maps_can_be_indexed() {
+    assert_runtime_error("\"abc\"[0];", "Only lists and maps can be indexed.");
+    assert_runtime_error(
+        "var n = 1;\nn[0] = 1;",
+        "Only lists and maps can be indexed.",
+    );
+}
+
+#[test]
+fn lists_have_no_other_properties() {
+    assert_runtime_error("[1].size;", "Undefined property size");
+}
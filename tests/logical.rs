@@ -0,0 +1,138 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn or_skips_right_operand_when_left_is_truthy() {
+    let output = run(r#"
+        var calls = 0;
+        fun touch() {
+          calls = calls + 1;
+          return true;
+        }
+        true or touch();
+        println(calls);
+        false or touch();
+        println(calls);
+    "#);
+
+    assert_eq!(output.stdout, "0\n1\n");
+    assert_eq!(output.stderr, "");
+}
+
+#[test]
+fn and_skips_right_operand_when_left_is_falsey() {
+    let output = run(r#"
+        var calls = 0;
+        fun touch() {
+          calls = calls + 1;
+          return true;
+        }
+        false and touch();
+        nil and touch();
+        println(calls);
+        true and touch();
+        println(calls);
+    "#);
+
+    assert_eq!(output.stdout, "0\n1\n");
+    assert_eq!(output.stderr, "");
+}
+
+#[test]
+fn and_guards_property_access_on_nil() {
+    let output = run(r#"
+        class Node {
+          init(value) {
+            this.value = value;
+          }
+        }
+        var x = nil;
+        println(x != nil and x.value);
+        x = Node(7);
+        println(x != nil and x.value);
+    "#);
+
+    assert_eq!(output.stdout, "false\n7\n");
+    assert_eq!(output.stderr, "");
+}
+
+#[test]
+fn or_provides_default_for_nil() {
+    let output = run(r#"
+        var name = nil;
+        println(name or "anonymous");
+        name = "lox";
+        println(name or "anonymous");
+    "#);
+
+    assert_eq!(output.stdout, "anonymous\nlox\n");
+}
+
+#[test]
+fn logical_operators_return_operands() {
+    let output = run(r#"
+        println(1 or 2);
+        println(nil or "right");
+        println(false or nil);
+        println(1 and 2);
+        println(nil and 2);
+        println(false and nil);
+        println("" and "empty strings are truthy");
+        println(0 or "zero is truthy");
+    "#);
+
+    assert_eq!(
+        output.stdout,
+        "1\nright\nnil\n2\nnil\nfalse\nempty strings are truthy\n0\n"
+    );
+}
+
+#[test]
+fn chained_operators_stop_at_first_deciding_operand() {
+    let output = run(r#"
+        var log = "";
+        fun step(name, value) {
+          log = log + name;
+          return value;
+        }
+        println(step("a", false) or step("b", nil) or step("c", 3) or step("d", 4));
+        println(log);
+        log = "";
+        println(step("a", 1) and step("b", 2) and step("c", false) and step("d", 4));
+        println(log);
+    "#);
+
+    assert_eq!(output.stdout, "3\nabc\nfalse\nabc\n");
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let output = run(r#"
+        println(true or false and false);
+        println(false and true or "fallback");
+    "#);
+
+    assert_eq!(output.stdout, "true\nfallback\n");
+}
+
+#[test]
+fn errors_in_skipped_operand_are_not_raised() {
+    let output = run(r#"
+        println(true or undefinedFunction());
+        println(false and -"not a number");
+    "#);
+
+    assert_eq!(output.stdout, "true\nfalse\n");
+    assert_eq!(output.stderr, "");
+}
+
+#[test]
+fn comparison_operands_bind_tighter_than_logical_operators() {
+    let output = run(r#"
+        println(1 + 1 == 2 and 2 < 1 + 2);
+        println(1 != 1 or 3 >= 2 * 2);
+    "#);
+
+    assert_eq!(output.stdout, "true\nfalse\n");
+}
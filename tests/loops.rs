@@ -0,0 +1,48 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn break_outside_loop_is_an_error() {
+    let output = run("if (true) break;");
+
+    assert!(
+        output
+            .stderr
+            .contains("Can't use 'break' outside of a loop."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn continue_outside_loop_is_an_error() {
+    let output = run("continue;");
+
+    assert!(
+        output
+            .stderr
+            .contains("Can't use 'continue' outside of a loop."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn functions_do_not_inherit_the_enclosing_loop() {
+    let output = run(r#"
+        while (true) {
+          fun escape() {
+            break;
+          }
+        }
+    "#);
+
+    assert!(
+        output
+            .stderr
+            .contains("Can't use 'break' outside of a loop."),
+        "{}",
+        output.stderr
+    );
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::assert_runtime_error;
+
+#[test]
+fn missing_keys_are_an_error() {
+    assert_runtime_error("var m = {\"a\": 1};\nm[\"b\"];", "Undefined key b.");
+    assert_runtime_error("var m = {};\nm.remove(\"x\");", "Undefined key x.");
+}
+
+#[test]
+fn keys_must_have_a_stable_hash() {
+    let message = "Map keys must be numbers, strings, booleans or nil.";
+    assert_runtime_error("var m = {};\nm[[1]] = 1;", message);
+    assert_runtime_error("var m = {};\nm[fun () {}] = 1;", message);
+    assert_runtime_error("var m = {};\nm.has({});", message);
+}
+
+#[test]
+fn nan_is_not_a_key() {
+    assert_runtime_error(
+        "var m = {};\nm[0 / 0] = 1;",
+        "NaN can't be used as a map key.",
+    );
+}
+
+#[test]
+fn maps_have_no_other_properties() {
+    assert_runtime_error("var m = {};\nm.size;", "Undefined property size");
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::{assert_scan_error, run};
+
+#[test]
+fn prefixed_literals_need_valid_digits() {
+    assert_scan_error("0x;", "Expect digits after '0x'.");
+    assert_scan_error("0b2;", "Invalid digit '2' in binary literal.");
+    assert_scan_error("0o8;", "Invalid digit '8' in octal literal.");
+    assert_scan_error("0x1.5;", "Invalid digit '.' in hexadecimal literal.");
+}
+
+#[test]
+fn separators_must_be_between_digits() {
+    for source in ["1__0;", "1_;", "0x_ff;"] {
+        assert_scan_error(source, "Digit separators must be between digits.");
+    }
+}
+
+#[test]
+fn exponents_and_fractions_need_digits() {
+    assert_scan_error("1e;", "Expect digits in exponent.");
+    assert_scan_error("1e+;", "Expect digits in exponent.");
+    assert_scan_error("1._5;", "Expect digit after '.'.");
+}
+
+#[test]
+fn oversized_literals_are_errors() {
+    assert_scan_error("0xFFFFFFFFFFFFFFFFFFFF;", "Number literal is too large.");
+}
+
+#[test]
+fn a_leading_underscore_makes_an_identifier() {
+    let output = run("var _1 = 2;\nprintln(_1);");
+
+    assert_eq!(output.stdout, "2\n");
+    assert_eq!(output.stderr, "");
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::summary;
+use rlox::error::LoxError;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::statements::Stmt;
+
+fn parse_with_errors(source: &str) -> (Vec<Stmt>, Vec<LoxError>) {
+    let tokens = Scanner::new(source.chars().peekable())
+        .scan_tokens()
+        .unwrap();
+    Parser::new(tokens).parse()
+}
+
+fn parse(source: &str) -> Vec<Stmt> {
+    let (stmts, errors) = parse_with_errors(source);
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    stmts
+}
+
+fn doc(stmt: &Stmt) -> Option<&str> {
+    match stmt {
+        Stmt::Var(v) => v.doc.as_deref(),
+        Stmt::Func(f) => f.doc.as_deref(),
+        Stmt::Class(c) => c.doc.as_deref(),
+        _ => panic!("{:?} can't be documented", stmt),
+    }
+}
+
+#[test]
+fn doc_comments_attach_to_declarations() {
+    let stmts = parse(
+        "/// The answer.
+        var answer = 42;
+
+        /// Adds two numbers.
+        ///
+        /// Both must be numbers.
+        fun add(a, b) { return a + b; }
+
+        /// A point.
+        class Point {}
+        ",
+    );
+
+    assert_eq!(doc(&stmts[0]), Some("The answer."));
+    assert_eq!(
+        doc(&stmts[1]),
+        Some("Adds two numbers.\n\nBoth must be numbers.")
+    );
+    assert_eq!(doc(&stmts[2]), Some("A point."));
+}
+
+#[test]
+fn doc_comments_attach_to_methods() {
+    let stmts = parse(
+        "class Point {
+          /// Makes a point.
+          init(x, y) {}
+
+          norm() {}
+        }",
+    );
+
+    let Stmt::Class(class) = &stmts[0] else {
+        panic!("{:?} isn't a class", stmts[0]);
+    };
+    assert_eq!(class.doc, None);
+    assert_eq!(class.methods[0].doc.as_deref(), Some("Makes a point."));
+    assert_eq!(class.methods[1].doc, None);
+}
+
+#[test]
+fn plain_comments_are_not_docs() {
+    let stmts = parse(
+        "// Not a doc.
+        //// Nor this.
+        var x = 1;",
+    );
+
+    assert_eq!(doc(&stmts[0]), None);
+}
+
+#[test]
+fn doc_comment_with_nothing_after_it_is_dropped() {
+    let stmts = parse(
+        "var x = 1;
+        /// Documents nothing.",
+    );
+
+    assert_eq!(stmts.len(), 1);
+    assert_eq!(doc(&stmts[0]), None);
+}
+
+#[test]
+fn doc_comment_before_a_statement_is_dropped() {
+    let stmts = parse(
+        "/// Documents nothing.
+        println(1);
+        var x = 1;",
+    );
+
+    assert_eq!(doc(&stmts[1]), None);
+}
+
+#[test]
+fn every_syntax_error_is_reported() {
+    let (stmts, errors) = parse_with_errors(
+        "var x = 1 +;
+        print(1;
+        var = 2;
+        var ok = 3;",
+    );
+
+    assert_eq!(
+        summary(&errors),
+        [
+            (1, "Expect expression"),
+            (2, "Expect ')' after arguments."),
+            (3, "Expect variable name."),
+        ]
+    );
+    assert_eq!(stmts.len(), 1);
+    assert!(matches!(&stmts[0], Stmt::Var(v) if v.token.lexeme == "ok"));
+}
+
+#[test]
+fn errors_inside_declarations_are_reported() {
+    let (_, errors) = parse_with_errors(
+        "fun f() {
+          var = 1;
+        }
+        class A {
+          m( {}
+        }
+        var y = );",
+    );
+
+    assert_eq!(
+        summary(&errors),
+        [
+            (2, "Expect variable name."),
+            (5, "Expect parameter name."),
+            (7, "Expect expression"),
+        ]
+    );
+}
+
+#[test]
+fn unclosed_block_is_reported_once() {
+    let (_, errors) = parse_with_errors("{ var a = 1;");
+
+    assert_eq!(summary(&errors), [(1, "Expect '}' after block.")]);
+}
+
+#[test]
+fn error_in_a_nested_block_is_reported_once() {
+    let (stmts, errors) = parse_with_errors(
+        r#"try {
+          {"a": 1}["b"];
+        } catch (e) {
+          println(e);
+        }
+        var ok = 1;"#,
+    );
+
+    assert_eq!(summary(&errors), [(2, "Expect ';' after value.")]);
+    assert_eq!(stmts.len(), 2);
+}
+
+#[test]
+fn unterminated_block_comment_is_an_error() {
+    let errors = Scanner::new("println(1);\n/* open /* nested */".chars().peekable())
+        .scan_tokens()
+        .unwrap_err();
+
+    assert_eq!(summary(&errors), [(2, "Unterminated block comment.")]);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::run_repl;
+
+#[test]
+fn expressions_are_printed_without_a_semicolon() {
+    let output = run_repl("1 + 2\n{\"a\": 1}\n1 + 2 // a comment\n");
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "3\n{a: 1}\n3\n");
+}
+
+#[test]
+fn statements_run_as_they_are() {
+    let output = run_repl("var x = 3;\n{ println(x * 2); }\nx\n");
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "6\n3\n");
+}
+
+#[test]
+fn input_that_is_neither_reports_statement_errors() {
+    let output = run_repl("var = 1;\n");
+
+    assert!(
+        output.stderr.contains("Expect variable name."),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn piped_input_gets_no_prompts() {
+    let output = run_repl("fun add(a, b) {\n  return a + b;\n}\nprint(add(1, 2));\n");
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "3");
+}
+
+#[test]
+fn only_a_lone_r_starts_a_raw_string() {
+    let output = run_repl("println(nil or\"a\\\"b\");\nprintln(r\"a\\\");\n");
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "a\"b\na\\\n");
+}
+
+#[test]
+fn unbalanced_input_continues_on_the_next_line() {
+    let output = run_repl(
+        "fun f() {\n  return [\n    1,\n    2];\n}\nf()\n\"multi\nline\"\n/* open\ncomment */ 5\n",
+    );
+
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "[1, 2]\nmulti\nline\n5\n");
+}
+
+#[test]
+fn errors_do_not_end_the_session() {
+    let output = run_repl("var x = 1;\nprintln(undefined);\nx + 1\n");
+
+    assert!(
+        output.stderr.contains("Undefined variable."),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.stdout, "2\n");
+    assert_eq!(output.status, Some(0));
+}
+
+#[test]
+fn syntax_error_in_a_lambda_body_is_reported() {
+    let output = run_repl("fun (a) { retur a; }\n");
+
+    assert!(
+        output.stderr.contains("Expect ';' after value."),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.stdout, "");
+}
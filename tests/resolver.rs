@@ -0,0 +1,88 @@
+mod common;
+
+use common::summary;
+use rlox::error::LoxError;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+
+fn resolve(source: &str) -> Vec<LoxError> {
+    let tokens = Scanner::new(source.chars().peekable())
+        .scan_tokens()
+        .unwrap();
+    let (stmts, errors) = Parser::new(tokens).parse();
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::new(Vec::new(), &mut interpreter);
+    resolver.resolve_stmts(&stmts).err().unwrap_or_default()
+}
+
+#[test]
+fn every_error_is_collected_in_order() {
+    let errors = resolve(
+        "return 1;
+        println(this);
+        {
+          var a = a;
+          var b = 1;
+          var b = 2;
+        }
+        class A {
+          init() {
+            return 1;
+          }
+        }
+        break;",
+    );
+
+    assert_eq!(
+        summary(&errors),
+        [
+            (1, "Can't return from top-level code."),
+            (2, "Can't use `this` keyword from top-level code."),
+            (4, "Can't read local variable in its own initializer."),
+            (6, "Already a variable with this name in this scope."),
+            (10, "Can't return a value from an initializer."),
+            (13, "Can't use 'break' outside of a loop."),
+        ]
+    );
+    assert!(errors.iter().all(|e| e.kind() == "Resolve"));
+}
+
+#[test]
+fn errors_in_one_function_do_not_hide_the_next() {
+    let errors = resolve(
+        "fun f() {
+          var x = x;
+        }
+        fun g() {
+          var y = y;
+        }",
+    );
+
+    assert_eq!(
+        summary(&errors),
+        [
+            (2, "Can't read local variable in its own initializer."),
+            (5, "Can't read local variable in its own initializer."),
+        ]
+    );
+}
+
+#[test]
+fn valid_programs_have_no_errors() {
+    let errors = resolve(
+        "var a = 1;
+        var a = 2;
+        fun f(x) {
+          var y = x;
+          return y;
+        }
+        class A {}
+        class B < A {}",
+    );
+
+    assert_eq!(summary(&errors), []);
+}
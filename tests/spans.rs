@@ -0,0 +1,86 @@
+use rlox::parser::Parser;
+use rlox::scanner::{Scanner, Token, TokenType};
+
+fn tokens(source: &str) -> Vec<Token> {
+    Scanner::new(source.chars().peekable())
+        .scan_tokens()
+        .unwrap()
+}
+
+#[test]
+fn tokens_know_their_line_and_column() {
+    let tokens = tokens("var x = 1;\n  print(x);");
+
+    let positions: Vec<(u64, u64)> = tokens.iter().map(|t| (t.line, t.column)).collect();
+    assert_eq!(
+        positions,
+        [
+            (1, 1),
+            (1, 5),
+            (1, 7),
+            (1, 9),
+            (1, 10),
+            (2, 3),
+            (2, 8),
+            (2, 9),
+            (2, 10),
+            (2, 11),
+            (2, 12),
+        ]
+    );
+}
+
+#[test]
+fn spans_are_byte_offsets_and_columns_count_characters() {
+    let source = "\"éé\" + x";
+    let tokens = tokens(source);
+
+    let x = &tokens[2];
+    assert_eq!(x.column, 8);
+    assert_eq!((x.span.start, x.span.end), (9, 10));
+    assert_eq!(&source[x.span.start..x.span.end], "x");
+}
+
+#[test]
+fn tokens_spanning_lines_start_on_their_first() {
+    let source = "var s = \"a\nb\";\nx";
+    let tokens = tokens(source);
+
+    let string = &tokens[3];
+    assert_eq!(string.token_type, TokenType::STRING);
+    assert_eq!((string.line, string.column), (1, 9));
+    assert_eq!(&source[string.span.start..string.span.end], "\"a\nb\"");
+
+    let semicolon = &tokens[4];
+    assert_eq!((semicolon.line, semicolon.column), (2, 3));
+}
+
+#[test]
+fn eof_is_an_empty_span_at_the_end() {
+    let source = "x;";
+    let eof = tokens(source).pop().unwrap();
+
+    assert_eq!(eof.token_type, TokenType::EOF);
+    assert_eq!((eof.span.start, eof.span.end), (2, 2));
+}
+
+#[test]
+fn statements_span_their_source() {
+    let source = "var x = 1;\nclass A { m() {} }\nwhile (x) x = nil;\nx + 1;";
+    let (stmts, errors) = Parser::new(tokens(source)).parse();
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let text: Vec<&str> = stmts
+        .iter()
+        .map(|s| &source[s.span().start..s.span().end])
+        .collect();
+    assert_eq!(
+        text,
+        [
+            "var x = 1;",
+            "class A { m() {} }",
+            "while (x) x = nil;",
+            "x + 1"
+        ]
+    );
+}
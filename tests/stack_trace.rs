@@ -0,0 +1,92 @@
+mod common;
+
+use common::run_with;
+
+/// The `stack trace:` lines printed for `source`, checking that both backends
+/// print the same ones.
+fn trace(source: &str) -> Vec<String> {
+    let tree_walker = trace_lines(&run_with(source, &[]).stderr);
+    let vm = trace_lines(&run_with(source, &["--vm"]).stderr);
+    assert_eq!(tree_walker, vm);
+
+    tree_walker
+}
+
+fn trace_lines(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .skip_while(|line| !line.ends_with("stack trace:"))
+        .skip(1)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[test]
+fn trace_lists_every_call_innermost_first() {
+    let trace = trace(
+        "class Point {
+          norm() {
+            return this.missing;
+          }
+        }
+        fun outer() {
+          var f = fun () { return Point().norm(); };
+          return f();
+        }
+        outer();",
+    );
+
+    assert_eq!(
+        trace,
+        [
+            "[line 3] in Point.norm()",
+            "[line 7] in lambda()",
+            "[line 8] in outer()",
+            "[line 10] in script",
+        ]
+    );
+}
+
+#[test]
+fn recursive_calls_each_get_a_line() {
+    let trace = trace(
+        "fun f(n) {
+          if (n == 0) return nil + 1;
+          return f(n - 1);
+        }
+        f(2);",
+    );
+
+    assert_eq!(
+        trace,
+        [
+            "[line 2] in f()",
+            "[line 3] in f()",
+            "[line 3] in f()",
+            "[line 5] in script",
+        ]
+    );
+}
+
+#[test]
+fn errors_in_initializers_name_the_class() {
+    let trace = trace(
+        "class Point {
+          init(x) {
+            this.x = -x;
+          }
+        }
+        Point(\"a\");",
+    );
+
+    assert_eq!(trace, ["[line 3] in Point.init()", "[line 6] in script"]);
+}
+
+#[test]
+fn top_level_errors_have_no_trace() {
+    let output = run_with("nil + 1;", &[]);
+
+    assert!(output.stderr.contains("runtime error"), "{}", output.stderr);
+    assert!(!output.stderr.contains("stack trace"), "{}", output.stderr);
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{assert_scan_error, run};
+
+#[test]
+fn unknown_escapes_are_errors() {
+    assert_scan_error("\"\\q\";", "Unknown escape sequence '\\q'.");
+}
+
+#[test]
+fn unicode_escapes_must_be_valid() {
+    for source in [
+        "\"\\u41\";",
+        "\"\\u{}\";",
+        "\"\\u{1234567}\";",
+        "\"\\u{D800}\";",
+    ] {
+        assert_scan_error(source, "Invalid unicode escape.");
+    }
+}
+
+#[test]
+fn unterminated_strings_are_errors() {
+    assert_scan_error("\"open", "Unterminated string.");
+    assert_scan_error("\"\"\"open\"", "Unterminated string.");
+    assert_scan_error("r\"open", "Unterminated string.");
+}
+
+#[test]
+fn every_bad_escape_is_reported() {
+    let output = run("\"\\q\";\n\"\\w\";");
+
+    assert!(output.stderr.contains("'\\q'"), "{}", output.stderr);
+    assert!(output.stderr.contains("'\\w'"), "{}", output.stderr);
+}